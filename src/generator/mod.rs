@@ -1,6 +1,13 @@
-use std::{fs::File, io::Write, path::Path};
+use std::{
+    fs::File,
+    io::{BufRead, Write},
+    path::Path,
+};
 
-use crate::calc::haversine;
+use crate::{
+    calc::haversine,
+    json::{de, from_json::derive_json, ser},
+};
 use rand::{Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
 
@@ -12,11 +19,24 @@ pub struct Pair {
     y1: f64,
 }
 
+derive_json!(Pair { x0, x1, y0, y1 });
+
 #[derive(Serialize, Deserialize, Debug)]
 struct Output {
     pairs: Vec<Pair>,
 }
 
+derive_json!(Output { pairs });
+
+#[allow(dead_code)]
+pub fn read_pairs<R>(reader: R) -> Result<Vec<Pair>, de::Error>
+where
+    R: BufRead,
+{
+    let output: Output = de::from_reader(reader)?;
+    Ok(output.pairs)
+}
+
 #[allow(dead_code)]
 pub fn generate(path: &Path, count: u32) -> Result<f64, std::io::Error> {
     // Generate 64 points
@@ -54,7 +74,7 @@ pub fn generate(path: &Path, count: u32) -> Result<f64, std::io::Error> {
 
     let output = Output { pairs };
 
-    file.write_all(serde_json::to_vec(&output).unwrap().as_slice())?;
+    file.write_all(ser::to_vec(&output).unwrap().as_slice())?;
     file.flush()?;
 
     let sum_filename = path.to_str().unwrap().to_owned() + "_sum";
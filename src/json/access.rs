@@ -0,0 +1,336 @@
+use std::{collections::HashMap, ops::Index};
+
+use super::tokenizer::JsonValue;
+
+// Returned by indexing when the member or element is missing, so lookups can
+// be chained without checking each step: `value["pairs"][0]["x0"]`.
+static NULL: JsonValue = JsonValue::NullValue;
+
+// What a JsonValue can be looked up by: object keys and array indices.
+pub trait JsonIndex {
+    fn index_into<'a>(&self, value: &'a JsonValue) -> Option<&'a JsonValue>;
+}
+
+impl JsonIndex for str {
+    fn index_into<'a>(&self, value: &'a JsonValue) -> Option<&'a JsonValue> {
+        match value {
+            JsonValue::Object(map) => map.get(self),
+            _ => None,
+        }
+    }
+}
+
+impl JsonIndex for String {
+    fn index_into<'a>(&self, value: &'a JsonValue) -> Option<&'a JsonValue> {
+        self.as_str().index_into(value)
+    }
+}
+
+impl JsonIndex for usize {
+    fn index_into<'a>(&self, value: &'a JsonValue) -> Option<&'a JsonValue> {
+        match value {
+            JsonValue::Array(items) => items.get(*self),
+            _ => None,
+        }
+    }
+}
+
+impl<T> JsonIndex for &T
+where
+    T: JsonIndex + ?Sized,
+{
+    fn index_into<'a>(&self, value: &'a JsonValue) -> Option<&'a JsonValue> {
+        (**self).index_into(value)
+    }
+}
+
+impl JsonValue {
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            JsonValue::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonValue::TrueValue => Some(true),
+            JsonValue::FalseValue => Some(false),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(str) => Some(str),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<JsonValue>> {
+        match self {
+            JsonValue::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&HashMap<String, JsonValue>> {
+        match self {
+            JsonValue::Object(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        *self == JsonValue::NullValue
+    }
+
+    // None when the key or index is missing or the value is not a container
+    // of the matching kind.
+    pub fn get<I>(&self, index: I) -> Option<&JsonValue>
+    where
+        I: JsonIndex,
+    {
+        index.index_into(self)
+    }
+}
+
+impl<I> Index<I> for JsonValue
+where
+    I: JsonIndex,
+{
+    type Output = JsonValue;
+
+    fn index(&self, index: I) -> &JsonValue {
+        self.get(index).unwrap_or(&NULL)
+    }
+}
+
+macro_rules! number_from {
+    ($($number: ty),*) => {
+        $(
+            impl From<$number> for JsonValue {
+                fn from(number: $number) -> Self {
+                    JsonValue::Number(number as f64)
+                }
+            }
+        )*
+    };
+}
+
+number_from!(f64, f32, i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl From<bool> for JsonValue {
+    fn from(value: bool) -> Self {
+        if value {
+            JsonValue::TrueValue
+        } else {
+            JsonValue::FalseValue
+        }
+    }
+}
+
+impl From<String> for JsonValue {
+    fn from(str: String) -> Self {
+        JsonValue::String(str)
+    }
+}
+
+impl From<&str> for JsonValue {
+    fn from(str: &str) -> Self {
+        JsonValue::String(str.to_string())
+    }
+}
+
+impl<T> From<Option<T>> for JsonValue
+where
+    T: Into<JsonValue>,
+{
+    fn from(value: Option<T>) -> Self {
+        value.map_or(JsonValue::NullValue, Into::into)
+    }
+}
+
+impl<T> From<Vec<T>> for JsonValue
+where
+    T: Into<JsonValue>,
+{
+    fn from(items: Vec<T>) -> Self {
+        JsonValue::Array(items.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T> From<&[T]> for JsonValue
+where
+    T: Clone + Into<JsonValue>,
+{
+    fn from(items: &[T]) -> Self {
+        JsonValue::Array(items.iter().cloned().map(Into::into).collect())
+    }
+}
+
+impl<K, T> From<HashMap<K, T>> for JsonValue
+where
+    K: Into<String>,
+    T: Into<JsonValue>,
+{
+    fn from(map: HashMap<K, T>) -> Self {
+        JsonValue::Object(
+            map.into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        )
+    }
+}
+
+// Builds a JsonValue from JSON-like syntax. Keys are string literals or
+// parenthesized expressions, values are JSON literals or any Rust expression
+// with a From impl:
+//
+//     json!({"pairs": [{"x0": 1.5, "y0": -x}], "count": (n + 1), "ok": true})
+macro_rules! json {
+    (@array [$($done: expr,)*] []) => {
+        vec![$($done,)*]
+    };
+    (@array [$($done: expr,)*] [$($current: tt)+]) => {
+        vec![$($done,)* json!($($current)+)]
+    };
+    (@array [$($done: expr,)*] [$($current: tt)+] , $($rest: tt)*) => {
+        json!(@array [$($done,)* json!($($current)+),] [] $($rest)*)
+    };
+    (@array [$($done: expr,)*] [$($current: tt)*] $next: tt $($rest: tt)*) => {
+        json!(@array [$($done,)*] [$($current)* $next] $($rest)*)
+    };
+
+    (@object $object: ident) => {};
+    (@object $object: ident $key: tt : $($rest: tt)+) => {
+        json!(@member $object $key [] $($rest)+)
+    };
+    (@member $object: ident $key: tt [$($value: tt)+]) => {
+        $object.insert(($key).to_string(), json!($($value)+));
+    };
+    (@member $object: ident $key: tt [$($value: tt)+] , $($rest: tt)*) => {
+        $object.insert(($key).to_string(), json!($($value)+));
+        json!(@object $object $($rest)*);
+    };
+    (@member $object: ident $key: tt [$($value: tt)*] $next: tt $($rest: tt)*) => {
+        json!(@member $object $key [$($value)* $next] $($rest)*)
+    };
+
+    (null) => {
+        $crate::json::tokenizer::JsonValue::NullValue
+    };
+    (true) => {
+        $crate::json::tokenizer::JsonValue::TrueValue
+    };
+    (false) => {
+        $crate::json::tokenizer::JsonValue::FalseValue
+    };
+    ([ $($tt: tt)* ]) => {
+        $crate::json::tokenizer::JsonValue::Array(json!(@array [] [] $($tt)*))
+    };
+    ({ $($tt: tt)* }) => {
+        $crate::json::tokenizer::JsonValue::Object({
+            #[allow(unused_mut)]
+            let mut object = std::collections::HashMap::new();
+            json!(@object object $($tt)*);
+            object
+        })
+    };
+    ($other: expr) => {
+        $crate::json::tokenizer::JsonValue::from($other)
+    };
+}
+pub(crate) use json;
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::json::access::*;
+    use crate::json::de;
+
+    fn parse(input: &str) -> JsonValue {
+        de::from_str(input).unwrap()
+    }
+
+    #[test]
+    pub fn test_accessors() {
+        let value = parse("{\"pairs\": [{\"x0\": 1.5, \"name\": \"a\", \"ok\": true}]}");
+
+        assert_eq!(Some(1.5), value["pairs"][0]["x0"].as_f64());
+        assert_eq!(Some("a"), value["pairs"][0]["name"].as_str());
+        assert_eq!(Some(true), value["pairs"][0]["ok"].as_bool());
+        assert_eq!(1, value["pairs"].as_array().unwrap().len());
+        assert_eq!(1, value.as_object().unwrap().len());
+        assert_eq!(None, value["pairs"].as_object());
+        assert_eq!(None, value["pairs"][0]["name"].as_f64());
+    }
+
+    #[rstest]
+    #[case("missing")]
+    #[case("pairs/5")]
+    #[case("pairs/0/x0/deeper")]
+    #[case("pairs/name")]
+    pub fn test_index_missing_is_null(#[case] path: &str) {
+        let value = parse("{\"pairs\": [{\"x0\": 1.5}]}");
+
+        let found = path
+            .split('/')
+            .fold(&value, |value, token| match token.parse::<usize>() {
+                Ok(i) => &value[i],
+                Err(_) => &value[token],
+            });
+
+        assert!(found.is_null());
+    }
+
+    #[test]
+    pub fn test_get() {
+        let value = parse("{\"a\": [null, 2]}");
+
+        assert_eq!(Some(&JsonValue::NullValue), value["a"].get(0));
+        assert_eq!(Some(&JsonValue::Number(2.0)), value["a"].get(1));
+        assert_eq!(None, value["a"].get(2));
+        assert_eq!(None, value.get("b"));
+        assert_eq!(None, value.get(0));
+        assert_eq!(Some(&value["a"]), value.get(String::from("a")));
+    }
+
+    #[rstest]
+    #[case(JsonValue::from(3u8), "3")]
+    #[case(JsonValue::from(-2i64), "-2")]
+    #[case(JsonValue::from(0.5f32), "0.5")]
+    #[case(JsonValue::from(false), "false")]
+    #[case(JsonValue::from("é"), "\"é\"")]
+    #[case(JsonValue::from(None::<i32>), "null")]
+    #[case(JsonValue::from(vec![Some(1), None]), "[1, null]")]
+    #[case(JsonValue::from(&["a", "b"][..]), "[\"a\", \"b\"]")]
+    #[case(JsonValue::from(HashMap::from([("k", 1.5)])), "{\"k\": 1.5}")]
+    pub fn test_from(#[case] value: JsonValue, #[case] expected: &str) {
+        assert_eq!(parse(expected), value);
+    }
+
+    #[test]
+    pub fn test_json_macro() {
+        let x = 2.5;
+        let key = "computed";
+
+        let value = json!({
+            "pairs": [{"x0": 1.5, "y0": -x}, [], {}],
+            "count": (1 + 2),
+            "ok": true,
+            "none": null,
+            (key): [false, "s", x * 2.0,],
+        });
+
+        assert_eq!(
+            parse(
+                "{\"pairs\": [{\"x0\": 1.5, \"y0\": -2.5}, [], {}], \"count\": 3, \"ok\": true, \"none\": null, \"computed\": [false, \"s\", 5]}"
+            ),
+            value
+        );
+        assert_eq!(JsonValue::Number(-1.0), json!(-1));
+        assert_eq!(JsonValue::Array(vec![]), json!([]));
+    }
+}
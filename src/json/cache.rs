@@ -0,0 +1,445 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    os::unix::io::AsRawFd,
+    path::{Path, PathBuf},
+    ptr, slice,
+};
+
+use super::{
+    de::{self, Error, MAX_DEPTH},
+    index::modified_nanos,
+    tokenizer::JsonValue,
+};
+
+// Cache layout, all integers little-endian u64:
+//   "JSONTAPE" version source_length source_mtime_nanos source_hash
+//   tape_words string_bytes
+//   then the tape, then the bytes of every string back to back.
+// The tape lists values in document order, one word each with a tag in the
+// top byte. Containers hold their length, numbers are followed by their bits
+// and strings by their length, with the offset of their bytes as payload.
+const MAGIC: &[u8; 8] = b"JSONTAPE";
+const VERSION: u64 = 1;
+const HEADER_WORDS: usize = 7;
+
+const TAG_SHIFT: u32 = 56;
+const PAYLOAD_MASK: u64 = (1 << TAG_SHIFT) - 1;
+const NULL: u64 = b'n' as u64;
+const TRUE: u64 = b't' as u64;
+const FALSE: u64 = b'f' as u64;
+const NUMBER: u64 = b'd' as u64;
+const STRING: u64 = b's' as u64;
+const ARRAY: u64 = b'[' as u64;
+const OBJECT: u64 = b'{' as u64;
+
+#[derive(PartialEq, Debug)]
+pub enum CacheError {
+    Io(io::ErrorKind),
+    Parse(Error),
+    InvalidCache,
+    StaleCache,
+}
+
+impl From<io::Error> for CacheError {
+    fn from(error: io::Error) -> Self {
+        CacheError::Io(error.kind())
+    }
+}
+
+impl From<Error> for CacheError {
+    fn from(error: Error) -> Self {
+        CacheError::Parse(error)
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum CacheStatus {
+    Hit,
+    Written,
+}
+
+// A read-only view of a whole file. The pages are only read from disk as the
+// bytes are touched.
+struct Mmap {
+    data: *mut libc::c_void,
+    len: usize,
+}
+
+impl Mmap {
+    fn open(file: &File) -> io::Result<Mmap> {
+        let len = file.metadata()?.len() as usize;
+        if len == 0 {
+            return Ok(Mmap {
+                data: ptr::null_mut(),
+                len,
+            });
+        }
+        let data = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if data == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Mmap { data, len })
+    }
+
+    fn bytes(&self) -> &[u8] {
+        if self.len == 0 {
+            return &[];
+        }
+        unsafe { slice::from_raw_parts(self.data as *const u8, self.len) }
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        if self.len != 0 {
+            unsafe {
+                libc::munmap(self.data, self.len);
+            }
+        }
+    }
+}
+
+// 64-bit FNV-1a, enough to notice a file rewritten with the same size and
+// modification time.
+fn hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(0x100_0000_01b3)
+    })
+}
+
+pub fn cache_path(json_path: &Path) -> PathBuf {
+    let mut path = json_path.as_os_str().to_owned();
+    path.push(".tape");
+    PathBuf::from(path)
+}
+
+#[derive(Default)]
+struct Tape {
+    words: Vec<u64>,
+    strings: Vec<u8>,
+}
+
+impl Tape {
+    fn push_string(&mut self, str: &str) {
+        self.words
+            .push(STRING << TAG_SHIFT | self.strings.len() as u64);
+        self.words.push(str.len() as u64);
+        self.strings.extend_from_slice(str.as_bytes());
+    }
+
+    fn push(&mut self, value: &JsonValue) {
+        match value {
+            JsonValue::Object(map) => {
+                let mut keys: Vec<&String> = map.keys().collect();
+                keys.sort();
+                self.words.push(OBJECT << TAG_SHIFT | keys.len() as u64);
+                for key in keys {
+                    self.push_string(key);
+                    self.push(&map[key]);
+                }
+            }
+            JsonValue::Array(items) => {
+                self.words.push(ARRAY << TAG_SHIFT | items.len() as u64);
+                for item in items {
+                    self.push(item);
+                }
+            }
+            JsonValue::String(str) => self.push_string(str),
+            JsonValue::Number(number) => {
+                self.words.push(NUMBER << TAG_SHIFT);
+                self.words.push(number.to_bits());
+            }
+            JsonValue::TrueValue => self.words.push(TRUE << TAG_SHIFT),
+            JsonValue::FalseValue => self.words.push(FALSE << TAG_SHIFT),
+            JsonValue::NullValue => self.words.push(NULL << TAG_SHIFT),
+        }
+    }
+}
+
+// Rebuilds values from a mapped tape. Every length and offset is checked, so
+// a damaged cache is reported instead of read out of bounds. Nesting is held
+// to what the parser accepts, so neither can it overflow the stack.
+struct TapeReader<'a> {
+    tape: &'a [u8],
+    strings: &'a [u8],
+    next: usize,
+    depth: usize,
+}
+
+impl<'a> TapeReader<'a> {
+    fn word(&mut self) -> Result<u64, CacheError> {
+        let bytes = self
+            .tape
+            .get(self.next * 8..self.next * 8 + 8)
+            .ok_or(CacheError::InvalidCache)?;
+        self.next += 1;
+        Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn string(&mut self, offset: u64) -> Result<String, CacheError> {
+        let length = self.word()?;
+        let bytes = offset
+            .checked_add(length)
+            .and_then(|end| self.strings.get(offset as usize..end as usize))
+            .ok_or(CacheError::InvalidCache)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| CacheError::InvalidCache)
+    }
+
+    fn value(&mut self) -> Result<JsonValue, CacheError> {
+        let word = self.word()?;
+        let payload = word & PAYLOAD_MASK;
+        // Each entry takes at least one word, which bounds what to reserve.
+        let remaining = self.tape.len() / 8 - self.next;
+        match word >> TAG_SHIFT {
+            NULL => Ok(JsonValue::NullValue),
+            TRUE => Ok(JsonValue::TrueValue),
+            FALSE => Ok(JsonValue::FalseValue),
+            NUMBER => Ok(JsonValue::Number(f64::from_bits(self.word()?))),
+            STRING => self.string(payload).map(JsonValue::String),
+            ARRAY | OBJECT if self.depth == MAX_DEPTH => Err(CacheError::InvalidCache),
+            ARRAY => {
+                let mut items = Vec::with_capacity((payload as usize).min(remaining));
+                self.depth += 1;
+                for _ in 0..payload {
+                    items.push(self.value()?);
+                }
+                self.depth -= 1;
+                Ok(JsonValue::Array(items))
+            }
+            OBJECT => {
+                let mut members = HashMap::with_capacity((payload as usize).min(remaining));
+                self.depth += 1;
+                for _ in 0..payload {
+                    let key = self.word()?;
+                    if key >> TAG_SHIFT != STRING {
+                        return Err(CacheError::InvalidCache);
+                    }
+                    let key = self.string(key & PAYLOAD_MASK)?;
+                    members.insert(key, self.value()?);
+                }
+                self.depth -= 1;
+                Ok(JsonValue::Object(members))
+            }
+            _ => Err(CacheError::InvalidCache),
+        }
+    }
+}
+
+fn header_word(cache: &[u8], i: usize) -> u64 {
+    u64::from_le_bytes(cache[i * 8..i * 8 + 8].try_into().unwrap())
+}
+
+// Reads the cached value of a document if the cache was written for exactly
+// the current contents of the file.
+pub fn read_cache(json_path: &Path) -> Result<JsonValue, CacheError> {
+    let json = File::open(json_path)?;
+    let metadata = json.metadata()?;
+    let cache = Mmap::open(&File::open(cache_path(json_path))?)?;
+    let cache = cache.bytes();
+    if cache.len() < HEADER_WORDS * 8 || &cache[..8] != MAGIC || header_word(cache, 1) != VERSION {
+        return Err(CacheError::InvalidCache);
+    }
+    if header_word(cache, 2) != metadata.len() || header_word(cache, 3) != modified_nanos(&metadata)
+    {
+        return Err(CacheError::StaleCache);
+    }
+    if header_word(cache, 4) != hash(Mmap::open(&json)?.bytes()) {
+        return Err(CacheError::StaleCache);
+    }
+    let tape_end = (header_word(cache, 5) as usize)
+        .checked_mul(8)
+        .and_then(|size| size.checked_add(HEADER_WORDS * 8))
+        .ok_or(CacheError::InvalidCache)?;
+    let strings_end = tape_end
+        .checked_add(header_word(cache, 6) as usize)
+        .filter(|end| *end == cache.len())
+        .ok_or(CacheError::InvalidCache)?;
+    let mut reader = TapeReader {
+        tape: &cache[HEADER_WORDS * 8..tape_end],
+        strings: &cache[tape_end..strings_end],
+        next: 0,
+        depth: 0,
+    };
+    let value = reader.value()?;
+    if reader.next * 8 != reader.tape.len() {
+        return Err(CacheError::InvalidCache);
+    }
+    Ok(value)
+}
+
+fn write_cache(json_path: &Path, key: [u64; 3], value: &JsonValue) -> Result<(), CacheError> {
+    let mut tape = Tape::default();
+    tape.push(value);
+    let temporary = cache_path(json_path).with_extension("tape.tmp");
+    let mut writer = BufWriter::new(File::create(&temporary)?);
+    writer.write_all(MAGIC)?;
+    let [length, modified, hash] = key;
+    let header = [
+        VERSION,
+        length,
+        modified,
+        hash,
+        tape.words.len() as u64,
+        tape.strings.len() as u64,
+    ];
+    for word in header.iter().chain(&tape.words) {
+        writer.write_all(&word.to_le_bytes())?;
+    }
+    writer.write_all(&tape.strings)?;
+    writer.flush()?;
+    drop(writer);
+    // Readers never see a half-written cache.
+    fs::rename(temporary, cache_path(json_path))?;
+    Ok(())
+}
+
+// Returns the parsed document, from `<json_path>.tape` when that is still
+// valid for the file's size, modification time and contents. Otherwise the
+// file is parsed and the cache written for the next run.
+pub fn parse_cached(json_path: &Path) -> Result<(JsonValue, CacheStatus), CacheError> {
+    match read_cache(json_path) {
+        Ok(value) => return Ok((value, CacheStatus::Hit)),
+        Err(CacheError::Io(io::ErrorKind::NotFound)) if json_path.exists() => {}
+        Err(CacheError::InvalidCache | CacheError::StaleCache) => {}
+        Err(error) => return Err(error),
+    }
+    let json = File::open(json_path)?;
+    let metadata = json.metadata()?;
+    let source = Mmap::open(&json)?;
+    let value = de::from_reader(source.bytes())?;
+    let key = [
+        metadata.len(),
+        modified_nanos(&metadata),
+        hash(source.bytes()),
+    ];
+    write_cache(json_path, key, &value)?;
+    Ok((value, CacheStatus::Written))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs::FileTimes, process};
+
+    use crate::json::cache::*;
+    use crate::json::tokenizer::parse_str;
+
+    const DOCUMENT: &str = "{\"pairs\": [{\"x0\": 1.5, \"y0\": -2}, {\"x0\": 1e300, \"y0\": 0}], \"name\": \"r\\u00e9sum\\u00e9\", \"flags\": [true, false, null, [], {}]}";
+
+    fn temporary_json(name: &str, contents: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("cache-test-{}-{}.json", name, process::id()));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn remove(json_path: &Path) {
+        let _ = fs::remove_file(cache_path(json_path));
+        fs::remove_file(json_path).unwrap();
+    }
+
+    #[test]
+    pub fn test_parse_cached_hit_after_write() {
+        let json_path = temporary_json("hit", DOCUMENT);
+        let expected = parse_str(DOCUMENT).unwrap();
+
+        assert_eq!(
+            Ok((expected.clone(), CacheStatus::Written)),
+            parse_cached(&json_path)
+        );
+        assert_eq!(Ok((expected, CacheStatus::Hit)), parse_cached(&json_path));
+
+        remove(&json_path);
+    }
+
+    #[test]
+    pub fn test_cache_keyed_by_contents() {
+        let json_path = temporary_json("contents", "[1, 2, 3]");
+        parse_cached(&json_path).unwrap();
+        let modified = fs::metadata(&json_path).unwrap().modified().unwrap();
+
+        // Same size and modification time, different contents.
+        fs::write(&json_path, "[4, 5, 6]").unwrap();
+        File::options()
+            .write(true)
+            .open(&json_path)
+            .unwrap()
+            .set_times(FileTimes::new().set_modified(modified))
+            .unwrap();
+
+        assert_eq!(Err(CacheError::StaleCache), read_cache(&json_path));
+        assert_eq!(
+            Ok((parse_str("[4, 5, 6]").unwrap(), CacheStatus::Written)),
+            parse_cached(&json_path)
+        );
+
+        remove(&json_path);
+    }
+
+    #[test]
+    pub fn test_damaged_cache_is_rebuilt() {
+        let json_path = temporary_json("damaged", DOCUMENT);
+        parse_cached(&json_path).unwrap();
+        let mut cache = fs::read(cache_path(&json_path)).unwrap();
+        cache.truncate(cache.len() - 3);
+        fs::write(cache_path(&json_path), cache).unwrap();
+
+        assert_eq!(Err(CacheError::InvalidCache), read_cache(&json_path));
+        assert_eq!(
+            Ok((parse_str(DOCUMENT).unwrap(), CacheStatus::Written)),
+            parse_cached(&json_path)
+        );
+
+        remove(&json_path);
+    }
+
+    #[test]
+    pub fn test_cache_nesting_limit() {
+        let document = "[".repeat(MAX_DEPTH) + &"]".repeat(MAX_DEPTH);
+        let json_path = temporary_json("nesting", &document);
+        parse_cached(&json_path).unwrap();
+        assert_eq!(Ok(parse_str(&document).unwrap()), read_cache(&json_path));
+
+        // A tape one level deeper than the parser allows, under a valid key.
+        let metadata = fs::metadata(&json_path).unwrap();
+        let key = [
+            metadata.len(),
+            modified_nanos(&metadata),
+            hash(document.as_bytes()),
+        ];
+        let mut value = JsonValue::Array(vec![]);
+        for _ in 0..MAX_DEPTH {
+            value = JsonValue::Array(vec![value]);
+        }
+        write_cache(&json_path, key, &value).unwrap();
+        assert_eq!(Err(CacheError::InvalidCache), read_cache(&json_path));
+
+        remove(&json_path);
+    }
+
+    #[test]
+    pub fn test_parse_cached_errors() {
+        let json_path = temporary_json("invalid", "[1, 2");
+
+        assert!(matches!(
+            parse_cached(&json_path),
+            Err(CacheError::Parse(_))
+        ));
+        assert!(!cache_path(&json_path).exists());
+
+        remove(&json_path);
+        assert_eq!(
+            Err(CacheError::Io(io::ErrorKind::NotFound)),
+            parse_cached(&json_path)
+        );
+    }
+}
@@ -0,0 +1,535 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    io::{self, Write},
+    str,
+};
+
+use super::tokenizer::JsonValue;
+
+// RFC 8949 major types, the top three bits of every initial byte.
+const UNSIGNED: u8 = 0;
+const NEGATIVE: u8 = 1;
+const BYTES: u8 = 2;
+const TEXT: u8 = 3;
+const ARRAY: u8 = 4;
+const MAP: u8 = 5;
+const TAG: u8 = 6;
+
+const FALSE: u8 = 0xf4;
+const TRUE: u8 = 0xf5;
+const NULL: u8 = 0xf6;
+const HALF: u8 = 0xf9;
+const SINGLE: u8 = 0xfa;
+const DOUBLE: u8 = 0xfb;
+const BREAK: u8 = 0xff;
+const INDEFINITE: u8 = 31;
+
+// Only says that what follows is CBOR, so it is the one tag that can be
+// dropped without changing the meaning of the data.
+const SELF_DESCRIBED: u64 = 55799;
+
+// Deepest nesting of arrays, maps and tags decoded before giving up, so
+// untrusted input cannot overflow the stack.
+const MAX_DEPTH: usize = 128;
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum CborError {
+    UnexpectedEnd,
+    // Byte strings, undefined, other simple values and tags have no JSON
+    // counterpart. Holds the initial byte and its offset.
+    Unsupported(u8, usize),
+    InvalidUtf8(usize),
+    NonTextKey(usize),
+    // A chunk of an indefinite-length string that is not a definite string
+    // of the same type, or a break where no item can end.
+    InvalidIndefinite(usize),
+    // Nesting beyond MAX_DEPTH. Holds the offset of the item too deep.
+    TooDeep(usize),
+    TrailingBytes(usize),
+}
+
+impl fmt::Display for CborError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CborError::UnexpectedEnd => write!(f, "unexpected end of input"),
+            CborError::Unsupported(initial, offset) => {
+                write!(f, "unsupported item 0x{:02x} at byte {}", initial, offset)
+            }
+            CborError::InvalidUtf8(offset) => write!(f, "invalid UTF-8 at byte {}", offset),
+            CborError::NonTextKey(offset) => {
+                write!(f, "map key is not a text string at byte {}", offset)
+            }
+            CborError::InvalidIndefinite(offset) => {
+                write!(f, "invalid indefinite-length item at byte {}", offset)
+            }
+            CborError::TooDeep(offset) => write!(f, "nesting too deep at byte {}", offset),
+            CborError::TrailingBytes(offset) => {
+                write!(f, "unexpected data after the value at byte {}", offset)
+            }
+        }
+    }
+}
+
+fn write_head<W>(writer: &mut W, major: u8, argument: u64) -> io::Result<()>
+where
+    W: Write,
+{
+    let major = major << 5;
+    match argument {
+        0..=23 => writer.write_all(&[major | argument as u8]),
+        24..=0xff => writer.write_all(&[major | 24, argument as u8]),
+        0x100..=0xffff => {
+            writer.write_all(&[major | 25])?;
+            writer.write_all(&(argument as u16).to_be_bytes())
+        }
+        0x10000..=0xffff_ffff => {
+            writer.write_all(&[major | 26])?;
+            writer.write_all(&(argument as u32).to_be_bytes())
+        }
+        _ => {
+            writer.write_all(&[major | 27])?;
+            writer.write_all(&argument.to_be_bytes())
+        }
+    }
+}
+
+// The half-precision bits for a number only if they hold it exactly.
+fn to_f16_bits(number: f64) -> Option<u16> {
+    if number.is_nan() {
+        return Some(0x7e00);
+    }
+    let sign = if number.is_sign_negative() { 0x8000 } else { 0 };
+    let magnitude = number.abs();
+    if magnitude == 0.0 {
+        return Some(sign);
+    }
+    if magnitude.is_infinite() {
+        return Some(sign | 0x7c00);
+    }
+    let single = magnitude as f32;
+    if single as f64 != magnitude {
+        return None;
+    }
+    let bits = single.to_bits();
+    let exponent = (bits >> 23) as i32 - 127;
+    let mantissa = bits & 0x7f_ffff;
+    if (-14..=15).contains(&exponent) && mantissa & 0x1fff == 0 {
+        return Some(sign | ((exponent + 15) as u16) << 10 | (mantissa >> 13) as u16);
+    }
+    // Subnormal halves are whole multiples of 2^-24 below 2^-14.
+    let steps = magnitude * (1u64 << 24) as f64;
+    if steps < 1024.0 && steps.fract() == 0.0 {
+        return Some(sign | steps as u16);
+    }
+    None
+}
+
+fn from_f16_bits(bits: u16) -> f64 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = (bits >> 10) & 0x1f;
+    let mantissa = (bits & 0x3ff) as f64;
+    sign * match exponent {
+        0 => mantissa * 2f64.powi(-24),
+        0x1f if mantissa == 0.0 => f64::INFINITY,
+        0x1f => f64::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2f64.powi(exponent as i32 - 15),
+    }
+}
+
+// Whole numbers are written as integers, anything else as the shortest float
+// that reads back as exactly the same f64, including -0.0 and NaN.
+fn write_number<W>(writer: &mut W, number: f64) -> io::Result<()>
+where
+    W: Write,
+{
+    let is_integer = number.fract() == 0.0 && !(number == 0.0 && number.is_sign_negative());
+    if is_integer && (0.0..18446744073709551616.0).contains(&number) {
+        return write_head(writer, UNSIGNED, number as u64);
+    }
+    // The argument is -1 - number, taken after the cast because the float
+    // subtraction rounds away the 1 beyond 2^53. -2^64 itself would saturate.
+    if is_integer && (-18446744073709551616.0..0.0).contains(&number) {
+        let argument = match number {
+            -18446744073709551616.0 => u64::MAX,
+            _ => (-number) as u64 - 1,
+        };
+        return write_head(writer, NEGATIVE, argument);
+    }
+    if let Some(bits) = to_f16_bits(number) {
+        writer.write_all(&[HALF])?;
+        return writer.write_all(&bits.to_be_bytes());
+    }
+    if number as f32 as f64 == number {
+        writer.write_all(&[SINGLE])?;
+        return writer.write_all(&(number as f32).to_be_bytes());
+    }
+    writer.write_all(&[DOUBLE])?;
+    writer.write_all(&number.to_be_bytes())
+}
+
+fn write_text<W>(writer: &mut W, str: &str) -> io::Result<()>
+where
+    W: Write,
+{
+    write_head(writer, TEXT, str.len() as u64)?;
+    writer.write_all(str.as_bytes())
+}
+
+// Definite lengths everywhere and map keys in sorted order, so the same value
+// always encodes to the same bytes.
+pub fn write_cbor<W>(writer: &mut W, value: &JsonValue) -> io::Result<()>
+where
+    W: Write,
+{
+    match value {
+        JsonValue::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            write_head(writer, MAP, keys.len() as u64)?;
+            for key in keys {
+                write_text(writer, key)?;
+                write_cbor(writer, &map[key])?;
+            }
+            Ok(())
+        }
+        JsonValue::Array(items) => {
+            write_head(writer, ARRAY, items.len() as u64)?;
+            for item in items {
+                write_cbor(writer, item)?;
+            }
+            Ok(())
+        }
+        JsonValue::String(str) => write_text(writer, str),
+        JsonValue::Number(number) => write_number(writer, *number),
+        JsonValue::TrueValue => writer.write_all(&[TRUE]),
+        JsonValue::FalseValue => writer.write_all(&[FALSE]),
+        JsonValue::NullValue => writer.write_all(&[NULL]),
+    }
+}
+
+#[allow(dead_code)]
+pub fn to_cbor(value: &JsonValue) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    write_cbor(&mut bytes, value).unwrap();
+    bytes
+}
+
+struct Decoder<'a> {
+    input: &'a [u8],
+    offset: usize,
+    depth: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], CborError> {
+        let end = self
+            .offset
+            .checked_add(count)
+            .filter(|end| *end <= self.input.len())
+            .ok_or(CborError::UnexpectedEnd)?;
+        let bytes = &self.input[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    fn peek(&self) -> Result<u8, CborError> {
+        self.input
+            .get(self.offset)
+            .copied()
+            .ok_or(CborError::UnexpectedEnd)
+    }
+
+    // The argument of an initial byte, None for an indefinite length.
+    fn argument(&mut self, initial: u8, start: usize) -> Result<Option<u64>, CborError> {
+        let info = initial & 0x1f;
+        let size = match info {
+            0..=23 => return Ok(Some(info as u64)),
+            24 => 1,
+            25 => 2,
+            26 => 4,
+            27 => 8,
+            INDEFINITE => return Ok(None),
+            _ => return Err(CborError::Unsupported(initial, start)),
+        };
+        let bytes = self.take(size)?;
+        Ok(Some(
+            bytes.iter().fold(0, |value, b| value << 8 | *b as u64),
+        ))
+    }
+
+    fn length(&mut self, initial: u8, start: usize) -> Result<Option<usize>, CborError> {
+        match self.argument(initial, start)? {
+            // No item takes less than a byte, so a longer length cannot fit.
+            Some(length) if length > (self.input.len() - self.offset) as u64 => {
+                Err(CborError::UnexpectedEnd)
+            }
+            length => Ok(length.map(|length| length as usize)),
+        }
+    }
+
+    fn at_break(&mut self) -> Result<bool, CborError> {
+        if self.peek()? == BREAK {
+            self.offset += 1;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    fn text(&mut self, initial: u8, start: usize) -> Result<String, CborError> {
+        let Some(length) = self.length(initial, start)? else {
+            let mut text = String::new();
+            while !self.at_break()? {
+                let chunk_start = self.offset;
+                let chunk = self.take(1)?[0];
+                if chunk >> 5 != TEXT || chunk & 0x1f == INDEFINITE {
+                    return Err(CborError::InvalidIndefinite(chunk_start));
+                }
+                text.push_str(&self.text(chunk, chunk_start)?);
+            }
+            return Ok(text);
+        };
+        let bytes = self.take(length)?;
+        str::from_utf8(bytes)
+            .map(str::to_string)
+            .map_err(|_| CborError::InvalidUtf8(start))
+    }
+
+    fn value(&mut self) -> Result<JsonValue, CborError> {
+        if self.depth == MAX_DEPTH {
+            return Err(CborError::TooDeep(self.offset));
+        }
+        self.depth += 1;
+        let value = self.item();
+        self.depth -= 1;
+        value
+    }
+
+    fn item(&mut self) -> Result<JsonValue, CborError> {
+        let start = self.offset;
+        let initial = self.take(1)?[0];
+        match initial >> 5 {
+            UNSIGNED | NEGATIVE => {
+                let Some(argument) = self.argument(initial, start)? else {
+                    return Err(CborError::Unsupported(initial, start));
+                };
+                Ok(JsonValue::Number(match initial >> 5 {
+                    UNSIGNED => argument as f64,
+                    // Rounded once, from the exact -1 - argument.
+                    _ => -((argument as u128 + 1) as f64),
+                }))
+            }
+            BYTES => Err(CborError::Unsupported(initial, start)),
+            TEXT => self.text(initial, start).map(JsonValue::String),
+            ARRAY => {
+                let length = self.length(initial, start)?;
+                let mut items = Vec::with_capacity(length.unwrap_or_default());
+                match length {
+                    Some(length) => {
+                        for _ in 0..length {
+                            items.push(self.value()?);
+                        }
+                    }
+                    None => {
+                        while !self.at_break()? {
+                            items.push(self.value()?);
+                        }
+                    }
+                }
+                Ok(JsonValue::Array(items))
+            }
+            MAP => {
+                let length = self.length(initial, start)?;
+                let mut members = HashMap::new();
+                let mut remaining = length;
+                loop {
+                    match remaining {
+                        Some(0) => break,
+                        Some(ref mut count) => *count -= 1,
+                        None if self.at_break()? => break,
+                        None => {}
+                    }
+                    let key_start = self.offset;
+                    let key = self.take(1)?[0];
+                    if key >> 5 != TEXT {
+                        return Err(CborError::NonTextKey(key_start));
+                    }
+                    let key = self.text(key, key_start)?;
+                    members.insert(key, self.value()?);
+                }
+                Ok(JsonValue::Object(members))
+            }
+            TAG => match self.argument(initial, start)? {
+                Some(SELF_DESCRIBED) => self.value(),
+                _ => Err(CborError::Unsupported(initial, start)),
+            },
+            // Major type 7, simple values and floats.
+            _ => match initial {
+                FALSE => Ok(JsonValue::FalseValue),
+                TRUE => Ok(JsonValue::TrueValue),
+                NULL => Ok(JsonValue::NullValue),
+                HALF => {
+                    let bytes = self.take(2)?;
+                    Ok(JsonValue::Number(from_f16_bits(u16::from_be_bytes([
+                        bytes[0], bytes[1],
+                    ]))))
+                }
+                SINGLE => {
+                    let bytes = self.take(4)?;
+                    Ok(JsonValue::Number(
+                        f32::from_be_bytes(bytes.try_into().unwrap()) as f64,
+                    ))
+                }
+                DOUBLE => {
+                    let bytes = self.take(8)?;
+                    Ok(JsonValue::Number(f64::from_be_bytes(
+                        bytes.try_into().unwrap(),
+                    )))
+                }
+                BREAK => Err(CborError::InvalidIndefinite(start)),
+                _ => Err(CborError::Unsupported(initial, start)),
+            },
+        }
+    }
+}
+
+// Decodes exactly one data item, definite or indefinite length. Integers
+// beyond 2^53 are rounded to the nearest f64, as they would be from JSON.
+#[allow(dead_code)]
+pub fn from_cbor(input: &[u8]) -> Result<JsonValue, CborError> {
+    let mut decoder = Decoder {
+        input,
+        offset: 0,
+        depth: 0,
+    };
+    let value = decoder.value()?;
+    if decoder.offset != input.len() {
+        return Err(CborError::TrailingBytes(decoder.offset));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::json::cbor::*;
+    use crate::json::test_support::{assert_round_trips, hex, unhex};
+    use crate::json::tokenizer::parse_str;
+
+    // Examples from RFC 8949 appendix A. Whole numbers are integers here.
+    #[rstest]
+    #[case(0.0, "00")]
+    #[case(23.0, "17")]
+    #[case(24.0, "1818")]
+    #[case(1000.0, "1903e8")]
+    #[case(1000000.0, "1a000f4240")]
+    #[case(1000000000000.0, "1b000000e8d4a51000")]
+    #[case(-1.0, "20")]
+    #[case(-1000.0, "3903e7")]
+    #[case(18446744073709549568.0, "1bfffffffffffff800")]
+    #[case(-18446744073709551616.0, "3bffffffffffffffff")]
+    #[case(-1152921504606846976.0, "3b0fffffffffffffff")]
+    #[case(-9007199254740994.0, "3b0020000000000001")]
+    #[case(1e20, "fb4415af1d78b58c40")]
+    #[case(-0.0, "f98000")]
+    #[case(1.1, "fb3ff199999999999a")]
+    #[case(1.5, "f93e00")]
+    #[case(65504.5, "fa477fe080")]
+    #[case(3.4028234663852886e+38, "fa7f7fffff")]
+    #[case(5.960464477539063e-8, "f90001")]
+    #[case(0.00006103515625, "f90400")]
+    #[case(-4.1, "fbc010666666666666")]
+    #[case(f64::INFINITY, "f97c00")]
+    #[case(f64::NEG_INFINITY, "f9fc00")]
+    #[case(f64::NAN, "f97e00")]
+    pub fn test_encode_number(#[case] number: f64, #[case] expected: &str) {
+        let bytes = to_cbor(&JsonValue::Number(number));
+
+        assert_eq!(expected, hex(&bytes));
+        match from_cbor(&bytes) {
+            Ok(JsonValue::Number(decoded)) if number.is_nan() => assert!(decoded.is_nan()),
+            Ok(JsonValue::Number(decoded)) => assert_eq!(number.to_bits(), decoded.to_bits()),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[rstest]
+    #[case("\"\"", "60")]
+    #[case("\"IETF\"", "6449455446")]
+    #[case("\"\\u00fc\"", "62c3bc")]
+    #[case("[1, [2, 3], [4, 5]]", "8301820203820405")]
+    #[case("{\"b\": [2, 3], \"a\": 1}", "a26161016162820203")]
+    #[case("[true, false, null]", "83f5f4f6")]
+    pub fn test_encode_value(#[case] json: &str, #[case] expected: &str) {
+        assert_eq!(expected, hex(&to_cbor(&parse_str(json).unwrap())));
+    }
+
+    #[rstest]
+    #[case("9f018202039f0405ffff", "[1, [2, 3], [4, 5]]")]
+    #[case("9fff", "[]")]
+    #[case("7f657374726561646d696e67ff", "\"streaming\"")]
+    #[case("bf6346756ef563416d7421ff", "{\"Fun\": true, \"Amt\": -2}")]
+    #[case("a17f61616162ff9f01ff", "{\"ab\": [1]}")]
+    #[case("d9d9f7f93c00", "1.0")]
+    #[case("fa3fc00000", "1.5")]
+    pub fn test_decode(#[case] cbor: &str, #[case] json: &str) {
+        assert_eq!(Ok(parse_str(json).unwrap()), from_cbor(&unhex(cbor)));
+    }
+
+    #[rstest]
+    #[case("", CborError::UnexpectedEnd)]
+    #[case("0001", CborError::TrailingBytes(1))]
+    #[case("820102ff", CborError::TrailingBytes(3))]
+    #[case("9f0102", CborError::UnexpectedEnd)]
+    #[case("9bffffffffffffffff", CborError::UnexpectedEnd)]
+    #[case("4401020304", CborError::Unsupported(0x44, 0))]
+    #[case("81f7", CborError::Unsupported(0xf7, 1))]
+    #[case("c11a514b67b0", CborError::Unsupported(0xc1, 0))]
+    #[case("1c", CborError::Unsupported(0x1c, 0))]
+    #[case("62c328", CborError::InvalidUtf8(0))]
+    #[case("a10102", CborError::NonTextKey(1))]
+    #[case("7f6161016162ff", CborError::InvalidIndefinite(3))]
+    #[case("7f7f6161ffff", CborError::InvalidIndefinite(1))]
+    #[case("81ff", CborError::InvalidIndefinite(1))]
+    pub fn test_decode_errors(#[case] cbor: &str, #[case] expected: CborError) {
+        assert_eq!(Err(expected), from_cbor(&unhex(cbor)));
+    }
+
+    #[test]
+    pub fn test_error_messages() {
+        assert_eq!(
+            "unsupported item 0x44 at byte 0",
+            CborError::Unsupported(0x44, 0).to_string()
+        );
+        assert_eq!(
+            "map key is not a text string at byte 1",
+            CborError::NonTextKey(1).to_string()
+        );
+    }
+
+    #[rstest]
+    #[case("81", "")]
+    #[case("9f", "ff")]
+    #[case("a1616b", "")]
+    #[case("d9d9f7", "")]
+    pub fn test_decode_nesting_limit(#[case] open: &str, #[case] close: &str) {
+        let (open, close) = (unhex(open), unhex(close));
+        let mut nested = open.repeat(MAX_DEPTH - 1);
+        nested.push(0xf6);
+        nested.extend(close.repeat(MAX_DEPTH - 1));
+        assert!(from_cbor(&nested).is_ok());
+
+        // Deep enough to overflow the stack without the limit.
+        let deep = open.repeat(300_000);
+        assert_eq!(
+            Err(CborError::TooDeep(open.len() * MAX_DEPTH)),
+            from_cbor(&deep)
+        );
+    }
+
+    #[test]
+    pub fn test_round_trip_tokenizer_output() {
+        assert_round_trips(to_cbor, from_cbor);
+    }
+}
@@ -0,0 +1,431 @@
+use std::{env, fs::File, io::BufReader, path::Path, process, time::Instant};
+
+use super::{
+    cache::{parse_cached, CacheStatus},
+    de::{self, Error},
+    diagnostic::{render, RenderOptions},
+    diff::{diff_with_tolerance, render_text, to_patch, Tolerance},
+    extract::{Extractor, Scalar},
+    footprint::footprint,
+    index::{build_index, IndexedArray},
+    lexer::ParseOptions,
+    patch::patch_to_json,
+    path::parse_path,
+    recover::parse_recovering,
+    schema::{pairs_schema, validate},
+    spans::parse_with_spans,
+    tokenizer::{tokenized_traced, JsonValue, TraceToken},
+    verify::verify_path,
+    writer::to_string,
+};
+
+fn print_usage() {
+    eprintln!("Usage:");
+    eprintln!("  json query <expression> <file> [--lenient]");
+    eprintln!(
+        "  json diff <from> <to> [--patch] [--absolute <tolerance>] [--relative <tolerance>] [--lenient]"
+    );
+    eprintln!("  json check <file> [--color]");
+    eprintln!("  json validate <file> [--schema <schema file>] [--lenient]");
+    eprintln!("  json stats <file> <path>...");
+    eprintln!("  json index <file> [member]");
+    eprintln!("  json element <file> <index>");
+    eprintln!("  json cache <file>");
+    eprintln!("  json tokens <file>");
+    eprintln!("  json memory <file> [--lenient]");
+    eprintln!("  json verify <file or directory>...");
+}
+
+// Query, diff and validate take --lenient for ParseOptions::lenient().
+fn read_json_file(path: &str, options: ParseOptions) -> JsonValue {
+    let Ok(file) = File::open(path) else {
+        eprintln!("Cannot open {}", path);
+        process::exit(1);
+    };
+    match de::from_reader_with_options(BufReader::new(file), options) {
+        Ok(value) => value,
+        Err(e) => {
+            report_parse_error(path, &e, false);
+            process::exit(1);
+        }
+    }
+}
+
+// Shows the failing part of the file. Colored when stderr is a terminal and
+// NO_COLOR is not set, unless forced on.
+fn report_parse_error(path: &str, error: &Error, force_color: bool) {
+    let options = RenderOptions {
+        color: force_color
+            || (unsafe { libc::isatty(libc::STDERR_FILENO) } == 1
+                && env::var_os("NO_COLOR").is_none()),
+        ..RenderOptions::default()
+    };
+    let rendered = File::open(path).and_then(|mut file| render(&mut file, path, error, options));
+    match rendered {
+        Ok(rendered) => eprint!("{}", rendered),
+        Err(_) => eprintln!("Cannot parse {}: {}", path, error),
+    }
+}
+
+fn query(args: &[String]) {
+    let (expression, path, options) = match args {
+        [expression, path] => (expression, path, ParseOptions::default()),
+        [expression, path, option] if option == "--lenient" => {
+            (expression, path, ParseOptions::lenient())
+        }
+        _ => {
+            print_usage();
+            process::exit(1);
+        }
+    };
+    let json_path = match parse_path(expression) {
+        Ok(json_path) => json_path,
+        Err(e) => {
+            eprintln!("Invalid expression {}: {:?}", expression, e);
+            process::exit(1);
+        }
+    };
+    let document = read_json_file(path, options);
+    for found in json_path.query(&document) {
+        println!("{}\t{}", found.pointer, to_string(found.value));
+    }
+}
+
+fn parse_tolerance(value: Option<&String>) -> f64 {
+    match value.map(|value| value.parse()) {
+        Some(Ok(tolerance)) => tolerance,
+        _ => {
+            print_usage();
+            process::exit(1);
+        }
+    }
+}
+
+fn diff(args: &[String]) {
+    let [from, to, options @ ..] = args else {
+        print_usage();
+        process::exit(1);
+    };
+    let mut as_patch = false;
+    let mut tolerance = Tolerance::default();
+    let mut parse_options = ParseOptions::default();
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--patch" => as_patch = true,
+            "--lenient" => parse_options = ParseOptions::lenient(),
+            "--absolute" => tolerance.absolute = parse_tolerance(options.next()),
+            "--relative" => tolerance.relative = parse_tolerance(options.next()),
+            _ => {
+                print_usage();
+                process::exit(1);
+            }
+        }
+    }
+    let changes = diff_with_tolerance(
+        &read_json_file(from, parse_options),
+        &read_json_file(to, parse_options),
+        &tolerance,
+    );
+    if as_patch {
+        println!("{}", to_string(&patch_to_json(&to_patch(&changes))));
+    } else {
+        print!("{}", render_text(&changes));
+    }
+}
+
+// Only checks that a file is well-formed JSON, reporting every error found
+// rather than just the first.
+fn check(args: &[String]) {
+    let (path, color) = match args {
+        [path] => (path, false),
+        [path, option] if option == "--color" => (path, true),
+        _ => {
+            print_usage();
+            process::exit(1);
+        }
+    };
+    let Ok(file) = File::open(path) else {
+        eprintln!("Cannot open {}", path);
+        process::exit(1);
+    };
+    let errors = parse_recovering(BufReader::new(file)).errors;
+    for error in &errors {
+        report_parse_error(path, error, color);
+    }
+    if !errors.is_empty() {
+        eprintln!("{} errors in {}", errors.len(), path);
+        process::exit(1);
+    }
+}
+
+// Checks a file against the built-in pairs schema unless another is given.
+fn validate_file(args: &[String]) {
+    let (args, options) = match args {
+        [args @ .., option] if option == "--lenient" => (args, ParseOptions::lenient()),
+        _ => (args, ParseOptions::default()),
+    };
+    let (path, schema) = match args {
+        [path] => (path, pairs_schema()),
+        [path, option, schema_path] if option == "--schema" => {
+            (path, read_json_file(schema_path, options))
+        }
+        _ => {
+            print_usage();
+            process::exit(1);
+        }
+    };
+    let Ok(file) = File::open(path) else {
+        eprintln!("Cannot open {}", path);
+        process::exit(1);
+    };
+    let (document, spans) = match parse_with_spans(BufReader::new(file), options) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            report_parse_error(path, &e, false);
+            process::exit(1);
+        }
+    };
+    let violations = validate(&schema, &document);
+    for violation in &violations {
+        match spans.value_span(&violation.pointer) {
+            Some(span) => println!("{} at {}", violation, span.start),
+            None => println!("{}", violation),
+        }
+    }
+    if !violations.is_empty() {
+        process::exit(1);
+    }
+}
+
+#[derive(Clone, Copy)]
+struct ColumnStats {
+    count: u64,
+    min: f64,
+    max: f64,
+    sum: f64,
+}
+
+// Streams the file once and summarizes the numbers found at each path, so
+// files of any size can be inspected in constant memory.
+fn stats(args: &[String]) {
+    let [path, expressions @ ..] = args else {
+        print_usage();
+        process::exit(1);
+    };
+    let expressions: Vec<&str> = expressions.iter().map(String::as_str).collect();
+    let extractor = match Extractor::new(&expressions) {
+        Ok(extractor) => extractor,
+        Err(e) => {
+            eprintln!("Invalid path: {:?}", e);
+            process::exit(1);
+        }
+    };
+    let Ok(file) = File::open(path) else {
+        eprintln!("Cannot open {}", path);
+        process::exit(1);
+    };
+    let mut columns = vec![
+        ColumnStats {
+            count: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            sum: 0.0,
+        };
+        expressions.len()
+    ];
+    let result = extractor.run(BufReader::new(file), |i, scalar| {
+        if let Scalar::Number(number) = scalar {
+            let column = &mut columns[i];
+            column.count += 1;
+            column.min = column.min.min(number);
+            column.max = column.max.max(number);
+            column.sum += number;
+        }
+    });
+    if let Err(e) = result {
+        report_parse_error(path, &e, false);
+        process::exit(1);
+    }
+    for (expression, column) in expressions.iter().zip(columns) {
+        if column.count == 0 {
+            println!("{}\tcount=0", expression);
+            continue;
+        }
+        println!(
+            "{}\tcount={} min={} max={} mean={}",
+            expression,
+            column.count,
+            column.min,
+            column.max,
+            column.sum / column.count as f64
+        );
+    }
+}
+
+fn index(args: &[String]) {
+    let (path, member) = match args {
+        [path] => (path, None),
+        [path, member] => (path, Some(member.as_str())),
+        _ => {
+            print_usage();
+            process::exit(1);
+        }
+    };
+    match build_index(Path::new(path), member) {
+        Ok(count) => println!("Indexed {} elements", count),
+        Err(e) => {
+            eprintln!("Cannot index {}: {:?}", path, e);
+            process::exit(1);
+        }
+    }
+}
+
+fn element(args: &[String]) {
+    let [path, i] = args else {
+        print_usage();
+        process::exit(1);
+    };
+    let Ok(i) = i.parse() else {
+        print_usage();
+        process::exit(1);
+    };
+    let result = IndexedArray::open(Path::new(path)).and_then(|mut array| array.get(i));
+    match result {
+        Ok(value) => println!("{}", to_string(&value)),
+        Err(e) => {
+            eprintln!("Cannot read element {} of {}: {:?}", i, path, e);
+            process::exit(1);
+        }
+    }
+}
+
+// Parses through the on-disk cache, writing it when missing or out of date.
+fn cache(args: &[String]) {
+    let [path] = args else {
+        print_usage();
+        process::exit(1);
+    };
+    let started = Instant::now();
+    match parse_cached(Path::new(path)) {
+        Ok((_, status)) => {
+            let action = match status {
+                CacheStatus::Hit => "Loaded cached",
+                CacheStatus::Written => "Parsed and cached",
+            };
+            println!("{} {} in {:?}", action, path, started.elapsed());
+        }
+        Err(e) => {
+            eprintln!("Cannot load {}: {:?}", path, e);
+            process::exit(1);
+        }
+    }
+}
+
+// Lists the tokens the tokenizer reads, with their byte offsets, up to the
+// first one it cannot make sense of.
+fn tokens(args: &[String]) {
+    let [path] = args else {
+        print_usage();
+        process::exit(1);
+    };
+    let Ok(file) = File::open(path) else {
+        eprintln!("Cannot open {}", path);
+        process::exit(1);
+    };
+    let mut reader = BufReader::new(file);
+    let mut print = |offset: u64, token: TraceToken| println!("{}\t{:?}", offset, token);
+    if let Err(e) = tokenized_traced(&mut reader, &mut print) {
+        eprintln!("Cannot parse {}: {:?}", path, e);
+        process::exit(1);
+    }
+}
+
+// Times a parse and shows how much memory the parsed document takes.
+// With --lenient, comments, trailing commas and the other JSONC and JSON5
+// extensions of ParseOptions are accepted.
+fn memory(args: &[String]) {
+    let (path, options) = match args {
+        [path] => (path, ParseOptions::default()),
+        [path, option] if option == "--lenient" => (path, ParseOptions::lenient()),
+        _ => {
+            print_usage();
+            process::exit(1);
+        }
+    };
+    let Ok(file) = File::open(path) else {
+        eprintln!("Cannot open {}", path);
+        process::exit(1);
+    };
+    let started = Instant::now();
+    let value: JsonValue = match de::from_reader_with_options(BufReader::new(file), options) {
+        Ok(value) => value,
+        Err(e) => {
+            report_parse_error(path, &e, false);
+            process::exit(1);
+        }
+    };
+    println!("Parsed {} in {:?}", path, started.elapsed());
+    print!("{}", footprint(&value));
+}
+
+// Checks json::tokenizer against serde_json on every given file, and every
+// .json file under the given directories.
+fn verify(args: &[String]) {
+    if args.is_empty() {
+        print_usage();
+        process::exit(1);
+    }
+    let mut files = 0;
+    let mut disagreements = 0;
+    for path in args {
+        let results = match verify_path(Path::new(path)) {
+            Ok(results) => results,
+            Err(e) => {
+                eprintln!("Cannot read {}: {}", path, e);
+                process::exit(1);
+            }
+        };
+        for (file, found) in results {
+            files += 1;
+            disagreements += found.len();
+            for disagreement in found {
+                let offset = match disagreement.offset {
+                    Some(offset) => format!("byte {}", offset),
+                    None => String::from("unknown offset"),
+                };
+                println!(
+                    "{}: \"{}\" at {}: {}",
+                    file.display(),
+                    disagreement.pointer,
+                    offset,
+                    disagreement.mismatch
+                );
+            }
+        }
+    }
+    println!("{} disagreements in {} files", disagreements, files);
+    if disagreements > 0 {
+        process::exit(1);
+    }
+}
+
+pub fn run(args: &[String]) {
+    match args.first().map(String::as_str) {
+        Some("query") => query(&args[1..]),
+        Some("diff") => diff(&args[1..]),
+        Some("check") => check(&args[1..]),
+        Some("validate") => validate_file(&args[1..]),
+        Some("stats") => stats(&args[1..]),
+        Some("index") => index(&args[1..]),
+        Some("element") => element(&args[1..]),
+        Some("cache") => cache(&args[1..]),
+        Some("tokens") => tokens(&args[1..]),
+        Some("memory") => memory(&args[1..]),
+        Some("verify") => verify(&args[1..]),
+        _ => {
+            print_usage();
+            process::exit(1);
+        }
+    }
+}
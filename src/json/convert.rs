@@ -0,0 +1,387 @@
+use std::{collections::HashMap, fmt};
+
+use serde::{
+    de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor},
+    ser::{Serialize, SerializeMap, Serializer},
+};
+
+use super::{from_json::exact_integer, pointer::escape_token, tokenizer::JsonValue};
+
+// Neither direction is lossless for every number: serde_json cannot hold NaN
+// or the infinities, and JsonValue keeps every number as an f64 so integers
+// beyond 2^53 may not survive. Those conversions fail instead of rounding.
+#[derive(PartialEq, Eq, Debug)]
+pub enum ConversionErrorKind {
+    NonFiniteNumber,
+    InexactInteger(String),
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct ConversionError {
+    pub pointer: String,
+    pub kind: ConversionErrorKind,
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ConversionErrorKind::NonFiniteNumber => write!(f, "non-finite number")?,
+            ConversionErrorKind::InexactInteger(integer) => {
+                write!(f, "integer {} has no exact f64 representation", integer)?
+            }
+        }
+        write!(f, " at \"{}\"", self.pointer)
+    }
+}
+
+const TWO_POW_63: f64 = 9_223_372_036_854_775_808.0;
+const TWO_POW_64: f64 = 18_446_744_073_709_551_616.0;
+
+// Casting back from f64 saturates, so 2^63 and 2^64 would otherwise look like
+// exact conversions of i64::MAX and u64::MAX.
+fn exact_i64(integer: i64) -> Option<f64> {
+    let number = integer as f64;
+    (number != TWO_POW_63 && number as i64 == integer).then_some(number)
+}
+
+fn exact_u64(integer: u64) -> Option<f64> {
+    let number = integer as f64;
+    (number != TWO_POW_64 && number as u64 == integer).then_some(number)
+}
+
+fn exact_number(number: &serde_json::Number) -> Option<f64> {
+    if let Some(integer) = number.as_u64() {
+        return exact_u64(integer);
+    }
+    if let Some(integer) = number.as_i64() {
+        return exact_i64(integer);
+    }
+    number.as_f64()
+}
+
+// Whole numbers become serde_json integers, the way serde_json reads them
+// from text, so converted values equal parsed ones. -0 stays a float.
+fn to_serde_json_number(number: f64) -> Option<serde_json::Number> {
+    if number != 0.0 || number.is_sign_positive() {
+        if let Some(integer) = exact_integer::<u64>(number) {
+            return Some(integer.into());
+        }
+        if let Some(integer) = exact_integer::<i64>(number) {
+            return Some(integer.into());
+        }
+    }
+    serde_json::Number::from_f64(number)
+}
+
+fn from_serde_json(pointer: &str, value: &serde_json::Value) -> Result<JsonValue, ConversionError> {
+    Ok(match value {
+        serde_json::Value::Null => JsonValue::NullValue,
+        serde_json::Value::Bool(true) => JsonValue::TrueValue,
+        serde_json::Value::Bool(false) => JsonValue::FalseValue,
+        serde_json::Value::Number(number) => {
+            JsonValue::Number(exact_number(number).ok_or_else(|| ConversionError {
+                pointer: pointer.to_string(),
+                kind: ConversionErrorKind::InexactInteger(number.to_string()),
+            })?)
+        }
+        serde_json::Value::String(str) => JsonValue::String(str.clone()),
+        serde_json::Value::Array(items) => JsonValue::Array(
+            items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| from_serde_json(&format!("{}/{}", pointer, i), item))
+                .collect::<Result<_, _>>()?,
+        ),
+        serde_json::Value::Object(map) => JsonValue::Object(
+            map.iter()
+                .map(|(key, value)| {
+                    let pointer = format!("{}/{}", pointer, escape_token(key));
+                    Ok((key.clone(), from_serde_json(&pointer, value)?))
+                })
+                .collect::<Result<_, _>>()?,
+        ),
+    })
+}
+
+fn to_serde_json(pointer: &str, value: &JsonValue) -> Result<serde_json::Value, ConversionError> {
+    Ok(match value {
+        JsonValue::NullValue => serde_json::Value::Null,
+        JsonValue::TrueValue => serde_json::Value::Bool(true),
+        JsonValue::FalseValue => serde_json::Value::Bool(false),
+        JsonValue::Number(number) => to_serde_json_number(*number)
+            .map(serde_json::Value::Number)
+            .ok_or_else(|| ConversionError {
+                pointer: pointer.to_string(),
+                kind: ConversionErrorKind::NonFiniteNumber,
+            })?,
+        JsonValue::String(str) => serde_json::Value::String(str.clone()),
+        JsonValue::Array(items) => serde_json::Value::Array(
+            items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| to_serde_json(&format!("{}/{}", pointer, i), item))
+                .collect::<Result<_, _>>()?,
+        ),
+        JsonValue::Object(map) => serde_json::Value::Object(
+            map.iter()
+                .map(|(key, value)| {
+                    let pointer = format!("{}/{}", pointer, escape_token(key));
+                    Ok((key.clone(), to_serde_json(&pointer, value)?))
+                })
+                .collect::<Result<_, _>>()?,
+        ),
+    })
+}
+
+impl TryFrom<&serde_json::Value> for JsonValue {
+    type Error = ConversionError;
+
+    fn try_from(value: &serde_json::Value) -> Result<Self, Self::Error> {
+        from_serde_json("", value)
+    }
+}
+
+impl TryFrom<serde_json::Value> for JsonValue {
+    type Error = ConversionError;
+
+    fn try_from(value: serde_json::Value) -> Result<Self, Self::Error> {
+        JsonValue::try_from(&value)
+    }
+}
+
+impl TryFrom<&JsonValue> for serde_json::Value {
+    type Error = ConversionError;
+
+    fn try_from(value: &JsonValue) -> Result<Self, Self::Error> {
+        to_serde_json("", value)
+    }
+}
+
+impl TryFrom<JsonValue> for serde_json::Value {
+    type Error = ConversionError;
+
+    fn try_from(value: JsonValue) -> Result<Self, Self::Error> {
+        serde_json::Value::try_from(&value)
+    }
+}
+
+// Object members are serialized in key order, the same as writer::write_value.
+impl Serialize for JsonValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            JsonValue::NullValue => serializer.serialize_unit(),
+            JsonValue::TrueValue => serializer.serialize_bool(true),
+            JsonValue::FalseValue => serializer.serialize_bool(false),
+            JsonValue::Number(number) => serializer.serialize_f64(*number),
+            JsonValue::String(str) => serializer.serialize_str(str),
+            JsonValue::Array(items) => serializer.collect_seq(items),
+            JsonValue::Object(map) => {
+                let mut keys: Vec<&String> = map.keys().collect();
+                keys.sort();
+                let mut object = serializer.serialize_map(Some(map.len()))?;
+                for key in keys {
+                    object.serialize_entry(key, &map[key])?;
+                }
+                object.end()
+            }
+        }
+    }
+}
+
+struct JsonValueVisitor;
+
+impl<'de> Visitor<'de> for JsonValueVisitor {
+    type Value = JsonValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "any JSON value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<JsonValue, E> {
+        Ok(if v {
+            JsonValue::TrueValue
+        } else {
+            JsonValue::FalseValue
+        })
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<JsonValue, E>
+    where
+        E: de::Error,
+    {
+        exact_i64(v)
+            .map(JsonValue::Number)
+            .ok_or_else(|| E::custom(format!("integer {} has no exact f64 representation", v)))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<JsonValue, E>
+    where
+        E: de::Error,
+    {
+        exact_u64(v)
+            .map(JsonValue::Number)
+            .ok_or_else(|| E::custom(format!("integer {} has no exact f64 representation", v)))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<JsonValue, E> {
+        Ok(JsonValue::Number(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<JsonValue, E> {
+        Ok(JsonValue::String(v.to_string()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<JsonValue, E> {
+        Ok(JsonValue::String(v))
+    }
+
+    fn visit_unit<E>(self) -> Result<JsonValue, E> {
+        Ok(JsonValue::NullValue)
+    }
+
+    fn visit_none<E>(self) -> Result<JsonValue, E> {
+        Ok(JsonValue::NullValue)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<JsonValue, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        JsonValue::deserialize(deserializer)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<JsonValue, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut items = Vec::new();
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(JsonValue::Array(items))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<JsonValue, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut result = HashMap::new();
+        while let Some((key, value)) = map.next_entry()? {
+            result.insert(key, value);
+        }
+        Ok(JsonValue::Object(result))
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonValue {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(JsonValueVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::json::convert::*;
+    use crate::json::tokenizer::parse_str;
+    use crate::json::{de, ser, writer};
+
+    const DOCUMENT: &str = "{\"pairs\": [{\"x0\": 1.5, \"y0\": -0.002, \"x1\": 3, \"y1\": -12}], \"count\": 4294967296, \"name\": \"a\\u00e9\", \"ok\": true, \"none\": null, \"n\": [false, 0]}";
+
+    #[test]
+    pub fn test_round_trip_through_serde_json_value() {
+        let value = parse_str(DOCUMENT).unwrap();
+
+        let converted = serde_json::Value::try_from(&value).unwrap();
+
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(DOCUMENT).unwrap(),
+            converted
+        );
+        assert_eq!(Ok(value), JsonValue::try_from(converted));
+    }
+
+    #[rstest]
+    #[case(1.0, "1")]
+    #[case(-7.0, "-7")]
+    #[case(18446744073709549568.0, "18446744073709549568")]
+    #[case(-9223372036854775808.0, "-9223372036854775808")]
+    #[case(1.5, "1.5")]
+    #[case(-0.0, "-0.0")]
+    #[case(1e300, "1e300")]
+    pub fn test_to_serde_json_number(#[case] number: f64, #[case] expected: &str) {
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(expected).unwrap(),
+            serde_json::Value::try_from(&JsonValue::Number(number)).unwrap()
+        );
+    }
+
+    #[rstest]
+    #[case("9007199254740992", Some(9007199254740992.0))]
+    #[case("9007199254740993", None)]
+    #[case("-9223372036854775808", Some(-9223372036854775808.0))]
+    #[case("9223372036854775807", None)]
+    #[case("18446744073709551615", None)]
+    #[case("0.1", Some(0.1))]
+    pub fn test_try_from_serde_json_numbers(#[case] input: &str, #[case] expected: Option<f64>) {
+        let value: serde_json::Value = serde_json::from_str(input).unwrap();
+
+        assert_eq!(
+            expected,
+            JsonValue::try_from(&value).ok().map(|value| match value {
+                JsonValue::Number(number) => number,
+                _ => unreachable!(),
+            })
+        );
+    }
+
+    #[test]
+    pub fn test_try_from_serde_json_error_pointer() {
+        let value: serde_json::Value =
+            serde_json::from_str("{\"a/b\": [0, 12345678901234567891]}").unwrap();
+
+        assert_eq!(
+            Err(ConversionError {
+                pointer: "/a~1b/1".to_string(),
+                kind: ConversionErrorKind::InexactInteger("12345678901234567891".to_string()),
+            }),
+            JsonValue::try_from(value)
+        );
+    }
+
+    #[test]
+    pub fn test_try_from_json_value_non_finite() {
+        let value = JsonValue::Array(vec![JsonValue::Number(f64::INFINITY)]);
+
+        assert_eq!(
+            Err(ConversionError {
+                pointer: "/0".to_string(),
+                kind: ConversionErrorKind::NonFiniteNumber,
+            }),
+            serde_json::Value::try_from(value)
+        );
+    }
+
+    #[test]
+    pub fn test_serialize_matches_writer() {
+        let value = parse_str(DOCUMENT).unwrap();
+
+        assert_eq!(writer::to_string(&value), ser::to_string(&value).unwrap());
+    }
+
+    #[test]
+    pub fn test_deserialize() {
+        let value = parse_str(DOCUMENT).unwrap();
+
+        assert_eq!(Ok(value.clone()), de::from_str::<JsonValue>(DOCUMENT));
+        assert_eq!(value, serde_json::from_str::<JsonValue>(DOCUMENT).unwrap());
+        assert!(serde_json::from_str::<JsonValue>("18446744073709551615").is_err());
+    }
+}
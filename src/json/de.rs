@@ -0,0 +1,714 @@
+use std::{fmt, io::BufRead};
+
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess,
+    VariantAccess, Visitor,
+};
+
+use super::{
+    from_json::exact_integer,
+    lexer::{LexedToken, Lexer, LexerError, ParseOptions, Position, Token},
+};
+
+#[derive(PartialEq, Debug)]
+pub struct Error {
+    pub message: String,
+    pub position: Option<Position>,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.position {
+            Some(position) => write!(f, "{} at {}", self.message, position),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        Error {
+            message: message.to_string(),
+            position: None,
+        }
+    }
+}
+
+impl From<LexerError> for Error {
+    fn from(error: LexerError) -> Self {
+        Error {
+            message: error.kind.to_string(),
+            position: Some(error.position),
+        }
+    }
+}
+
+// Deepest nesting of arrays and objects read before giving up, so untrusted
+// input cannot overflow the stack of the recursive readers.
+pub const MAX_DEPTH: usize = 128;
+
+pub fn too_deep(token: &LexedToken) -> Error {
+    Error {
+        message: format!("nesting deeper than {} levels", MAX_DEPTH),
+        position: Some(token.start),
+    }
+}
+
+pub fn unexpected(token: &LexedToken, expected: &str) -> Error {
+    Error {
+        message: format!("expected {} but found {}", expected, token.token),
+        position: Some(token.start),
+    }
+}
+
+// Drives serde visitors straight from the lexer's token stream, so typed
+// values are built without an intermediate JsonValue tree.
+pub struct Deserializer<R> {
+    lexer: Lexer<R>,
+    last_token_start: Position,
+    depth: usize,
+}
+
+impl<R> Deserializer<R>
+where
+    R: BufRead,
+{
+    pub fn new(reader: R) -> Deserializer<R> {
+        Deserializer::with_options(reader, ParseOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: ParseOptions) -> Deserializer<R> {
+        Deserializer::with_lexer(Lexer::with_options(reader, options))
+    }
+
+    pub fn with_lexer(lexer: Lexer<R>) -> Deserializer<R> {
+        Deserializer {
+            lexer,
+            last_token_start: Position::default(),
+            depth: 0,
+        }
+    }
+
+    fn enter(&mut self, token: &LexedToken) -> Result<(), Error> {
+        if self.depth == MAX_DEPTH {
+            return Err(too_deep(token));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn peek(&mut self) -> Result<&Token, Error> {
+        Ok(&self.lexer.peek()?.token)
+    }
+
+    fn next(&mut self) -> Result<LexedToken, Error> {
+        let token = self.lexer.next_token()?;
+        self.last_token_start = token.start;
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), Error> {
+        let token = self.next()?;
+        if token.token != expected {
+            return Err(unexpected(&token, &expected.to_string()));
+        }
+        Ok(())
+    }
+
+    fn next_number(&mut self) -> Result<f64, Error> {
+        match self.next()? {
+            LexedToken {
+                token: Token::Number(number),
+                ..
+            } => Ok(number),
+            token => Err(unexpected(&token, "number")),
+        }
+    }
+
+    // Serde errors raised by visitors have no position of their own, they are
+    // reported at the start of the token that was being deserialized.
+    fn with_position(&self, mut error: Error) -> Error {
+        if error.position.is_none() {
+            error.position = Some(self.last_token_start);
+        }
+        error
+    }
+
+    pub fn end(&mut self) -> Result<(), Error> {
+        let token = self.next()?;
+        if token.token != Token::EndOfInput {
+            return Err(unexpected(&token, "end of input"));
+        }
+        Ok(())
+    }
+}
+
+pub fn from_reader<R, T>(reader: R) -> Result<T, Error>
+where
+    R: BufRead,
+    T: DeserializeOwned,
+{
+    from_reader_with_options(reader, ParseOptions::default())
+}
+
+pub fn from_str<T>(input: &str) -> Result<T, Error>
+where
+    T: DeserializeOwned,
+{
+    from_reader(input.as_bytes())
+}
+
+pub fn from_reader_with_options<R, T>(reader: R, options: ParseOptions) -> Result<T, Error>
+where
+    R: BufRead,
+    T: DeserializeOwned,
+{
+    from_lexer(Lexer::with_options(reader, options))
+}
+
+// Deserializes exactly one value from the lexer and requires the input to end
+// right after it.
+pub fn from_lexer<R, T>(lexer: Lexer<R>) -> Result<T, Error>
+where
+    R: BufRead,
+    T: DeserializeOwned,
+{
+    let mut deserializer = Deserializer::with_lexer(lexer);
+    let value = T::deserialize(&mut deserializer).map_err(|e| deserializer.with_position(e))?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+#[allow(dead_code)]
+pub fn from_str_with_options<T>(input: &str, options: ParseOptions) -> Result<T, Error>
+where
+    T: DeserializeOwned,
+{
+    from_reader_with_options(input.as_bytes(), options)
+}
+
+// Deserializes one or more values separated by commas up to the end of the
+// input, as found between the brackets of a non-empty array.
+pub fn separated_from_lexer<R, T>(lexer: Lexer<R>) -> Result<Vec<T>, Error>
+where
+    R: BufRead,
+    T: DeserializeOwned,
+{
+    let mut deserializer = Deserializer::with_lexer(lexer);
+    let mut values = Vec::new();
+    loop {
+        values.push(T::deserialize(&mut deserializer).map_err(|e| deserializer.with_position(e))?);
+        let token = deserializer.next()?;
+        match token.token {
+            Token::Comma => continue,
+            Token::EndOfInput => return Ok(values),
+            _ => return Err(unexpected(&token, "`,`")),
+        }
+    }
+}
+
+macro_rules! deserialize_integer {
+    ($method: ident, $visit: ident, $integer: ty) => {
+        fn $method<V>(self, visitor: V) -> Result<V::Value, Error>
+        where
+            V: Visitor<'de>,
+        {
+            let number = self.next_number()?;
+            match exact_integer::<$integer>(number) {
+                Some(integer) => visitor.$visit(integer),
+                None => visitor.visit_f64(number),
+            }
+        }
+    };
+}
+
+impl<'de, R> de::Deserializer<'de> for &mut Deserializer<R>
+where
+    R: BufRead,
+{
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        let token = self.next()?;
+        match token.token {
+            Token::BeginObject => {
+                self.enter(&token)?;
+                let mut access = CommaSeparated::new(self, Token::EndObject);
+                let value = visitor.visit_map(&mut access)?;
+                access.finish()?;
+                self.depth -= 1;
+                Ok(value)
+            }
+            Token::BeginArray => {
+                self.enter(&token)?;
+                let mut access = CommaSeparated::new(self, Token::EndArray);
+                let value = visitor.visit_seq(&mut access)?;
+                access.finish()?;
+                self.depth -= 1;
+                Ok(value)
+            }
+            Token::String(str) => visitor.visit_string(str),
+            Token::Number(number) => visitor.visit_f64(number),
+            Token::TrueValue => visitor.visit_bool(true),
+            Token::FalseValue => visitor.visit_bool(false),
+            Token::NullValue => visitor.visit_unit(),
+            _ => Err(unexpected(&token, "value")),
+        }
+    }
+
+    deserialize_integer!(deserialize_i8, visit_i64, i64);
+    deserialize_integer!(deserialize_i16, visit_i64, i64);
+    deserialize_integer!(deserialize_i32, visit_i64, i64);
+    deserialize_integer!(deserialize_i64, visit_i64, i64);
+    deserialize_integer!(deserialize_u8, visit_u64, u64);
+    deserialize_integer!(deserialize_u16, visit_u64, u64);
+    deserialize_integer!(deserialize_u32, visit_u64, u64);
+    deserialize_integer!(deserialize_u64, visit_u64, u64);
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        if *self.peek()? == Token::NullValue {
+            self.next()?;
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // Unit variants are plain strings, every other variant is an object with
+    // the variant name as its single key.
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        let token = self.next()?;
+        match token.token {
+            Token::String(variant) => visitor.visit_enum(variant.into_deserializer()),
+            Token::BeginObject => {
+                self.enter(&token)?;
+                let value = visitor.visit_enum(Enum { de: &mut *self })?;
+                self.expect(Token::EndObject)?;
+                self.depth -= 1;
+                Ok(value)
+            }
+            _ => Err(unexpected(&token, "string or object")),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool f32 f64 char str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any i128 u128
+    }
+}
+
+struct CommaSeparated<'a, R> {
+    de: &'a mut Deserializer<R>,
+    end: Token,
+    first: bool,
+    finished: bool,
+}
+
+impl<'a, R> CommaSeparated<'a, R>
+where
+    R: BufRead,
+{
+    fn new(de: &'a mut Deserializer<R>, end: Token) -> CommaSeparated<'a, R> {
+        CommaSeparated {
+            de,
+            end,
+            first: true,
+            finished: false,
+        }
+    }
+
+    // Consumes the separator in front of the next element, or the closing
+    // bracket. Returns false once the closing bracket was read.
+    fn has_next(&mut self) -> Result<bool, Error> {
+        if *self.de.peek()? == self.end {
+            self.de.next()?;
+            self.finished = true;
+            return Ok(false);
+        }
+        if !self.first {
+            let token = self.de.next()?;
+            if token.token != Token::Comma {
+                return Err(unexpected(&token, &format!("`,` or {}", self.end)));
+            }
+            if self.de.lexer.options().trailing_commas && *self.de.peek()? == self.end {
+                self.de.next()?;
+                self.finished = true;
+                return Ok(false);
+            }
+        }
+        self.first = false;
+        Ok(true)
+    }
+
+    // Visitors for fixed-size targets such as tuples stop asking for elements
+    // once they have enough, the closing bracket still has to be there.
+    fn finish(&mut self) -> Result<(), Error> {
+        if self.finished {
+            return Ok(());
+        }
+        let token = self.de.next()?;
+        if token.token != self.end {
+            return Err(unexpected(&token, &self.end.to_string()));
+        }
+        Ok(())
+    }
+}
+
+impl<'de, R> SeqAccess<'de> for CommaSeparated<'_, R>
+where
+    R: BufRead,
+{
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        if !self.has_next()? {
+            return Ok(None);
+        }
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+}
+
+impl<'de, R> MapAccess<'de> for CommaSeparated<'_, R>
+where
+    R: BufRead,
+{
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        if !self.has_next()? {
+            return Ok(None);
+        }
+        // Bare identifiers only ever reach the parser as keys, anywhere else
+        // they are reported as unexpected tokens.
+        match self.de.peek()? {
+            Token::String(_) => seed.deserialize(&mut *self.de).map(Some),
+            Token::Identifier(key) => {
+                let key = key.clone();
+                self.de.next()?;
+                seed.deserialize(key.into_deserializer()).map(Some)
+            }
+            _ => {
+                let token = self.de.next()?;
+                Err(unexpected(&token, "string"))
+            }
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        self.de.expect(Token::Colon)?;
+        seed.deserialize(&mut *self.de)
+    }
+}
+
+struct Enum<'a, R> {
+    de: &'a mut Deserializer<R>,
+}
+
+impl<'de, R> EnumAccess<'de> for Enum<'_, R>
+where
+    R: BufRead,
+{
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self), Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(&mut *self.de)?;
+        self.de.expect(Token::Colon)?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, R> VariantAccess<'de> for Enum<'_, R>
+where
+    R: BufRead,
+{
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        de::Deserialize::deserialize(self.de)
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        de::Deserializer::deserialize_seq(self.de, visitor)
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        de::Deserializer::deserialize_map(self.de, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use rstest::rstest;
+    use serde::Deserialize;
+
+    use crate::json::de::*;
+    use crate::json::tokenizer::JsonValue;
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Pair {
+        x0: f64,
+        x1: f64,
+        y0: f64,
+        y1: f64,
+    }
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Output {
+        pairs: Vec<Pair>,
+    }
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    enum Shape {
+        Empty,
+        Circle(f64),
+        Rectangle { width: u32, height: u32 },
+    }
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Everything {
+        count: u8,
+        offset: i64,
+        name: String,
+        label: Option<String>,
+        flags: (bool, bool),
+        shapes: Vec<Shape>,
+        extra: HashMap<String, Vec<f32>>,
+    }
+
+    #[test]
+    pub fn test_from_reader_output() {
+        let input = "{\"pairs\": [{\"x0\": 1.5, \"x1\": -2, \"y0\": 3e1, \"y1\": 0}]}";
+
+        assert_eq!(
+            Ok(Output {
+                pairs: vec![Pair {
+                    x0: 1.5,
+                    x1: -2.0,
+                    y0: 30.0,
+                    y1: 0.0
+                }]
+            }),
+            from_reader(input.as_bytes())
+        );
+    }
+
+    #[test]
+    pub fn test_from_str_everything() {
+        let input = "{
+            \"count\": 3, \"offset\": -7, \"name\": \"a\\nb\", \"label\": null,
+            \"flags\": [true, false], \"ignored\": {\"deep\": [1, {}]},
+            \"shapes\": [\"Empty\", {\"Circle\": 2.5}, {\"Rectangle\": {\"width\": 1, \"height\": 2}}],
+            \"extra\": {\"k\": [0.5]}
+        }";
+
+        assert_eq!(
+            Ok(Everything {
+                count: 3,
+                offset: -7,
+                name: "a\nb".to_string(),
+                label: None,
+                flags: (true, false),
+                shapes: vec![
+                    Shape::Empty,
+                    Shape::Circle(2.5),
+                    Shape::Rectangle {
+                        width: 1,
+                        height: 2
+                    }
+                ],
+                extra: HashMap::from([("k".to_string(), vec![0.5])]),
+            }),
+            from_str(input)
+        );
+    }
+
+    #[rstest]
+    #[case(
+        "{\"pairs\": [{\"x0\": 1, \"x1\": 2, \"y0\": 3, \"y1\": true}]}",
+        1,
+        46
+    )]
+    #[case("{\"pairs\": [{\"x0\": 1, \"x1\": 2, \"y0\": 3}]}", 1, 38)]
+    #[case("{\"pairs\": [{\"x0\": 1 \"x1\": 2}]}", 1, 21)]
+    #[case("{\"pairs\": []} []", 1, 15)]
+    #[case(
+        "{\"pairs\": [{\"x0\": 1, \"x1\": 2, \"y0\": 3, \"y1\": 4, \"x0\": 5}]}",
+        1,
+        49
+    )]
+    #[case("{\"pairs\":\n [}", 2, 3)]
+    pub fn test_from_str_error_positions(
+        #[case] input: &str,
+        #[case] line: u64,
+        #[case] column: u64,
+    ) {
+        let error = from_str::<Output>(input).unwrap_err();
+        let position = error.position.unwrap();
+
+        assert_eq!((line, column), (position.line, position.column));
+    }
+
+    #[test]
+    pub fn test_from_str_error_message() {
+        let error = from_str::<Vec<f64>>("[1 2]").unwrap_err();
+
+        assert_eq!(
+            "expected `,` or `]` but found number at line 1 column 4 (byte 3)",
+            error.to_string()
+        );
+    }
+
+    // Lexer errors carry their own position, which must not be shown twice.
+    #[rstest]
+    #[case("[1.5e]", "invalid number at line 1 column 2 (byte 1)")]
+    #[case("[\"a\\x\"]", "invalid escape sequence at line 1 column 4 (byte 3)")]
+    #[case("[1, @]", "unexpected character `@` at line 1 column 5 (byte 4)")]
+    pub fn test_from_str_lexer_error_message(#[case] input: &str, #[case] expected: &str) {
+        assert_eq!(
+            expected,
+            from_str::<JsonValue>(input).unwrap_err().to_string()
+        );
+    }
+
+    #[rstest]
+    #[case("[", "]")]
+    #[case("{\"a\":", "}")]
+    pub fn test_from_str_nesting_limit(#[case] open: &str, #[case] close: &str) {
+        let nested = |depth: usize| open.repeat(depth) + "0" + &close.repeat(depth);
+
+        assert!(from_str::<JsonValue>(&nested(MAX_DEPTH)).is_ok());
+        // Deep enough to overflow the stack without the limit.
+        let error = from_str::<JsonValue>(&nested(200_000)).unwrap_err();
+        let offset = open.len() * MAX_DEPTH;
+        assert_eq!(
+            format!(
+                "nesting deeper than 128 levels at line 1 column {} (byte {})",
+                offset + 1,
+                offset
+            ),
+            error.to_string()
+        );
+    }
+
+    #[test]
+    pub fn test_from_str_lenient_config() {
+        let input = "// generated by hand
+            {
+                /* first pair */
+                pairs: [{x0: 0x10, 'x1': -Infinity, \"y0\": NaN, y1: 2,},],
+            }";
+
+        let output: Output = from_str_with_options(input, ParseOptions::lenient()).unwrap();
+
+        assert_eq!(16.0, output.pairs[0].x0);
+        assert_eq!(f64::NEG_INFINITY, output.pairs[0].x1);
+        assert!(output.pairs[0].y0.is_nan());
+        assert!(from_str::<Output>(input).is_err());
+    }
+
+    #[rstest]
+    #[case("[1, 2,] // end", ParseOptions { comments: true, trailing_commas: true, ..Default::default() })]
+    #[case("{'a': [1]}", ParseOptions { single_quotes: true, ..Default::default() })]
+    #[case("{a: [1]}", ParseOptions { unquoted_keys: true, ..Default::default() })]
+    #[case("[0x1]", ParseOptions { hex_numbers: true, ..Default::default() })]
+    #[case("[NaN]", ParseOptions { non_finite_numbers: true, ..Default::default() })]
+    pub fn test_from_str_single_extension(#[case] input: &str, #[case] options: ParseOptions) {
+        assert!(from_str::<JsonValue>(input).is_err());
+        assert!(from_str_with_options::<JsonValue>(input, options).is_ok());
+    }
+
+    #[rstest]
+    #[case("[,]")]
+    #[case("[1,,]")]
+    #[case("{\"a\": 1,,}")]
+    #[case("[abc]")]
+    #[case("{a: b}")]
+    pub fn test_from_str_lenient_errors(#[case] input: &str) {
+        assert!(from_str_with_options::<JsonValue>(input, ParseOptions::lenient()).is_err());
+    }
+
+    #[test]
+    pub fn test_from_str_integer_out_of_range() {
+        assert!(from_str::<u8>("256").is_err());
+        assert!(from_str::<u8>("1.5").is_err());
+        assert_eq!(Ok(255), from_str::<u8>("255"));
+    }
+
+    #[rstest]
+    #[case("9223372036854775808", None, Some(9223372036854775808))]
+    #[case(
+        "9223372036854774784",
+        Some(9223372036854774784),
+        Some(9223372036854774784)
+    )]
+    #[case("-9223372036854775808", Some(i64::MIN), None)]
+    #[case("18446744073709551616", None, None)]
+    #[case("18446744073709549568", None, Some(18446744073709549568))]
+    pub fn test_from_str_64_bit_boundaries(
+        #[case] input: &str,
+        #[case] signed: Option<i64>,
+        #[case] unsigned: Option<u64>,
+    ) {
+        assert_eq!(signed, from_str::<i64>(input).ok());
+        assert_eq!(unsigned, from_str::<u64>(input).ok());
+    }
+}
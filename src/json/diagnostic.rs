@@ -0,0 +1,342 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+use super::de::Error;
+
+// How far around the failure the input is read. Lines reaching past this are
+// shown cut off, so a single-line file of any size costs one small read.
+const RADIUS: u64 = 64 * 1024;
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+#[derive(Debug, Clone, Copy)]
+pub struct RenderOptions {
+    // Lines shown before and after the failing line.
+    pub context: usize,
+    // Characters shown of each line, ellipses not included.
+    pub width: usize,
+    pub color: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            context: 2,
+            width: 80,
+            color: false,
+        }
+    }
+}
+
+impl RenderOptions {
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", style, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+// One line of the window, with the column of its first character.
+struct SourceLine {
+    number: u64,
+    first_column: u64,
+    text: String,
+}
+
+fn is_continuation(b: u8) -> bool {
+    b & 0xC0 == 0x80
+}
+
+fn display_text(bytes: &[u8]) -> String {
+    let bytes = bytes.strip_suffix(b"\r").unwrap_or(bytes);
+    String::from_utf8_lossy(bytes)
+        .chars()
+        .map(|c| match c {
+            '\t' => ' ',
+            c if c.is_control() => '\u{FFFD}',
+            c => c,
+        })
+        .collect()
+}
+
+// Cuts the lines of `window` around the failure at `local`. Lines before the
+// failing one whose start was not read are dropped, the failing line itself
+// is kept and its columns are counted back from the failure.
+fn source_lines(
+    window: &[u8],
+    whole_start: bool,
+    local: usize,
+    line: u64,
+    column: u64,
+    context: usize,
+) -> Vec<SourceLine> {
+    let line_start = window[..local]
+        .iter()
+        .rposition(|b| *b == b'\n')
+        .map_or(0, |i| i + 1);
+    let line_end = window[local..]
+        .iter()
+        .position(|b| *b == b'\n')
+        .map_or(window.len(), |i| local + i);
+    let before = window[line_start..local]
+        .iter()
+        .filter(|b| !is_continuation(**b))
+        .count() as u64;
+    let mut lines = vec![SourceLine {
+        number: line,
+        first_column: column.saturating_sub(before).max(1),
+        text: display_text(&window[line_start..line_end]),
+    }];
+
+    let mut end = line_start;
+    while lines.len() <= context && end > 0 {
+        let start = window[..end - 1]
+            .iter()
+            .rposition(|b| *b == b'\n')
+            .map_or(0, |i| i + 1);
+        if start == 0 && !whole_start {
+            break;
+        }
+        lines.insert(
+            0,
+            SourceLine {
+                number: lines[0].number - 1,
+                first_column: 1,
+                text: display_text(&window[start..end - 1]),
+            },
+        );
+        end = start;
+    }
+
+    let mut start = line_end + 1;
+    for number in line + 1..=line + context as u64 {
+        if start > window.len() {
+            break;
+        }
+        let end = window[start..]
+            .iter()
+            .position(|b| *b == b'\n')
+            .map_or(window.len(), |i| start + i);
+        lines.push(SourceLine {
+            number,
+            first_column: 1,
+            text: display_text(&window[start..end]),
+        });
+        start = end + 1;
+    }
+    lines
+}
+
+// The part of a line between two columns, with ellipses where it was cut.
+fn clip(line: &SourceLine, from: u64, width: usize) -> (bool, String) {
+    let skip = from.saturating_sub(line.first_column) as usize;
+    let mut chars = line.text.chars().skip(skip);
+    let shown: String = chars.by_ref().take(width).collect();
+    let cut_before = from > 1 && (line.first_column > 1 || skip > 0);
+    let mut text = String::new();
+    if cut_before {
+        text.push_str("...");
+    }
+    text.push_str(&shown);
+    if chars.next().is_some() {
+        text.push_str("...");
+    }
+    (cut_before, text)
+}
+
+// Renders an error like a compiler would: the message, where it happened,
+// and the lines around it with a caret under the failing character. Only a
+// bounded part of the input around the failure is read.
+pub fn render<R>(
+    source: &mut R,
+    name: &str,
+    error: &Error,
+    options: RenderOptions,
+) -> io::Result<String>
+where
+    R: Read + Seek,
+{
+    let heading = format!(
+        "{}{}\n",
+        options.paint(RED, "error"),
+        options.paint(BOLD, &format!(": {}", error.message))
+    );
+    let Some(position) = error.position else {
+        return Ok(heading);
+    };
+
+    let mut begin = position.offset.saturating_sub(RADIUS);
+    source.seek(SeekFrom::Start(begin))?;
+    let mut window = Vec::new();
+    source.take(2 * RADIUS).read_to_end(&mut window)?;
+    let skipped = window
+        .iter()
+        .take_while(|b| begin > 0 && is_continuation(**b))
+        .count();
+    window.drain(..skipped);
+    begin += skipped as u64;
+    let local = ((position.offset - begin) as usize).min(window.len());
+    let lines = source_lines(
+        &window,
+        begin == 0,
+        local,
+        position.line,
+        position.column,
+        options.context,
+    );
+
+    let from = if (position.column as usize) < options.width {
+        1
+    } else {
+        position.column - options.width as u64 / 2
+    };
+    let gutter = lines.last().map_or(1, |line| line.number.to_string().len());
+    let bar = options.paint(BLUE, "|");
+    let mut rendered = heading;
+    rendered.push_str(&format!(
+        "{:gutter$}{} {}:{}:{} (byte {})\n",
+        "",
+        options.paint(BLUE, "-->"),
+        name,
+        position.line,
+        position.column,
+        position.offset
+    ));
+    rendered.push_str(&format!("{:gutter$} {}\n", "", bar));
+    for line in &lines {
+        let (cut_before, text) = clip(line, from, options.width);
+        let number = options.paint(BLUE, &format!("{:>gutter$}", line.number));
+        if text.is_empty() {
+            rendered.push_str(&format!("{} {}\n", number, bar));
+        } else {
+            rendered.push_str(&format!("{} {} {}\n", number, bar, text));
+        }
+        if line.number == position.line {
+            let indent = (position.column - from) as usize + if cut_before { 3 } else { 0 };
+            rendered.push_str(&format!(
+                "{:gutter$} {} {:indent$}{}\n",
+                "",
+                bar,
+                "",
+                options.paint(RED, "^")
+            ));
+        }
+    }
+    Ok(rendered)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use rstest::rstest;
+
+    use crate::json::de;
+    use crate::json::diagnostic::*;
+    use crate::json::tokenizer::JsonValue;
+
+    fn render_str(input: &str, options: RenderOptions) -> String {
+        let error = de::from_str::<JsonValue>(input).unwrap_err();
+        render(&mut Cursor::new(input), "input.json", &error, options).unwrap()
+    }
+
+    #[test]
+    pub fn test_render_single_line() {
+        assert_eq!(
+            "error: expected `,` or `]` but found number\n \
+             --> input.json:1:13 (byte 12)\n  \
+             |\n\
+             1 | {\"a\": [1, 2 3], \"x0\": 1}\n  \
+             |             ^\n",
+            render_str("{\"a\": [1, 2 3], \"x0\": 1}", RenderOptions::default())
+        );
+    }
+
+    #[test]
+    pub fn test_render_context_lines() {
+        let input = "[\n  1,\n  2,\n  3\n  4,\n  5,\n  6,\n  7\n]";
+
+        assert_eq!(
+            "error: expected `,` or `]` but found number\n \
+             --> input.json:5:3 (byte 18)\n  \
+             |\n\
+             3 |   2,\n\
+             4 |   3\n\
+             5 |   4,\n  \
+             |   ^\n\
+             6 |   5,\n\
+             7 |   6,\n",
+            render_str(input, RenderOptions::default())
+        );
+    }
+
+    #[rstest]
+    #[case("[1, 2", "1 | [1, 2\n  |      ^\n")]
+    #[case("[1, 2\n", "1 | [1, 2\n2 |\n  | ^\n")]
+    pub fn test_render_end_of_input(#[case] input: &str, #[case] expected: &str) {
+        assert!(render_str(input, RenderOptions::default()).ends_with(expected));
+    }
+
+    #[test]
+    pub fn test_render_truncates_long_lines() {
+        let input = format!("[{}true false]", "1, ".repeat(1000));
+        let options = RenderOptions {
+            width: 20,
+            ..RenderOptions::default()
+        };
+
+        let rendered = render_str(&input, options);
+
+        assert!(rendered.contains("--> input.json:1:3007 (byte 3006)"));
+        assert!(rendered.ends_with(
+            "1 | ..., 1, true false]\n  \
+             |              ^\n"
+        ));
+    }
+
+    #[test]
+    pub fn test_render_counts_columns_in_characters() {
+        assert!(
+            render_str("[\"éé\" 1]", RenderOptions::default()).ends_with(
+                "1 | [\"éé\" 1]\n  \
+             |       ^\n"
+            )
+        );
+    }
+
+    #[test]
+    pub fn test_render_color() {
+        let options = RenderOptions {
+            color: true,
+            ..RenderOptions::default()
+        };
+
+        let rendered = render_str("[1 2]", options);
+
+        assert!(rendered.starts_with("\x1b[1;31merror\x1b[0m\x1b[1m: expected"));
+        assert!(rendered.contains("\x1b[1;31m^\x1b[0m"));
+    }
+
+    #[test]
+    pub fn test_render_without_position() {
+        let error = Error {
+            message: "missing field `pairs`".to_string(),
+            position: None,
+        };
+
+        assert_eq!(
+            "error: missing field `pairs`\n",
+            render(
+                &mut Cursor::new(""),
+                "input.json",
+                &error,
+                RenderOptions::default()
+            )
+            .unwrap()
+        );
+    }
+}
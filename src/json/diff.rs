@@ -0,0 +1,262 @@
+use std::collections::HashSet;
+
+use super::{
+    patch::PatchOperation, pointer::escape_token, tokenizer::JsonValue, writer::to_string,
+};
+
+#[derive(PartialEq, Debug)]
+pub enum Change {
+    Added {
+        pointer: String,
+        value: JsonValue,
+    },
+    Removed {
+        pointer: String,
+        value: JsonValue,
+    },
+    Changed {
+        pointer: String,
+        from: JsonValue,
+        to: JsonValue,
+    },
+}
+
+// Two numbers are considered equal when they are within `absolute` of each
+// other, or within `relative` times the larger magnitude of the two.
+#[derive(Default, Debug, Clone, Copy)]
+pub struct Tolerance {
+    pub absolute: f64,
+    pub relative: f64,
+}
+
+impl Tolerance {
+    fn numbers_equal(&self, from: f64, to: f64) -> bool {
+        if from == to {
+            return true;
+        }
+        let difference = (from - to).abs();
+        difference <= self.absolute || difference <= self.relative * from.abs().max(to.abs())
+    }
+}
+
+fn child_pointer(pointer: &str, token: &str) -> String {
+    format!("{}/{}", pointer, escape_token(token))
+}
+
+fn diff_values(
+    pointer: String,
+    from: &JsonValue,
+    to: &JsonValue,
+    tolerance: &Tolerance,
+    changes: &mut Vec<Change>,
+) {
+    match (from, to) {
+        (JsonValue::Number(f), JsonValue::Number(t)) if tolerance.numbers_equal(*f, *t) => {}
+        (JsonValue::Object(f), JsonValue::Object(t)) => {
+            let mut keys: Vec<&String> = f
+                .keys()
+                .chain(t.keys())
+                .collect::<HashSet<_>>()
+                .into_iter()
+                .collect();
+            keys.sort();
+            for key in keys {
+                let pointer = child_pointer(&pointer, key);
+                match (f.get(key), t.get(key)) {
+                    (Some(f), Some(t)) => diff_values(pointer, f, t, tolerance, changes),
+                    (Some(f), None) => changes.push(Change::Removed {
+                        pointer,
+                        value: f.clone(),
+                    }),
+                    (None, Some(t)) => changes.push(Change::Added {
+                        pointer,
+                        value: t.clone(),
+                    }),
+                    (None, None) => unreachable!(),
+                }
+            }
+        }
+        (JsonValue::Array(f), JsonValue::Array(t)) => {
+            for (i, (f, t)) in f.iter().zip(t.iter()).enumerate() {
+                diff_values(
+                    child_pointer(&pointer, &i.to_string()),
+                    f,
+                    t,
+                    tolerance,
+                    changes,
+                );
+            }
+            for (i, t) in t.iter().enumerate().skip(f.len()) {
+                changes.push(Change::Added {
+                    pointer: child_pointer(&pointer, &i.to_string()),
+                    value: t.clone(),
+                });
+            }
+            // Trailing removals are listed from the last element backwards so
+            // that the indices stay valid when the changes are applied in order.
+            for (i, f) in f.iter().enumerate().skip(t.len()).rev() {
+                changes.push(Change::Removed {
+                    pointer: child_pointer(&pointer, &i.to_string()),
+                    value: f.clone(),
+                });
+            }
+        }
+        (f, t) if f == t => {}
+        (f, t) => changes.push(Change::Changed {
+            pointer,
+            from: f.clone(),
+            to: t.clone(),
+        }),
+    }
+}
+
+#[allow(dead_code)]
+pub fn diff(from: &JsonValue, to: &JsonValue) -> Vec<Change> {
+    diff_with_tolerance(from, to, &Tolerance::default())
+}
+
+pub fn diff_with_tolerance(from: &JsonValue, to: &JsonValue, tolerance: &Tolerance) -> Vec<Change> {
+    let mut changes = Vec::new();
+    diff_values(String::new(), from, to, tolerance, &mut changes);
+    changes
+}
+
+pub fn render_text(changes: &[Change]) -> String {
+    changes
+        .iter()
+        .map(|change| match change {
+            Change::Added { pointer, value } => format!("+ {}: {}\n", pointer, to_string(value)),
+            Change::Removed { pointer, value } => format!("- {}: {}\n", pointer, to_string(value)),
+            Change::Changed { pointer, from, to } => {
+                format!("~ {}: {} -> {}\n", pointer, to_string(from), to_string(to))
+            }
+        })
+        .collect()
+}
+
+pub fn to_patch(changes: &[Change]) -> Vec<PatchOperation> {
+    changes
+        .iter()
+        .map(|change| match change {
+            Change::Added { pointer, value } => PatchOperation::Add {
+                path: pointer.clone(),
+                value: value.clone(),
+            },
+            Change::Removed { pointer, .. } => PatchOperation::Remove {
+                path: pointer.clone(),
+            },
+            Change::Changed { pointer, to, .. } => PatchOperation::Replace {
+                path: pointer.clone(),
+                value: to.clone(),
+            },
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::json::diff::*;
+    use crate::json::patch::{apply_patch, patch_to_json};
+    use crate::json::tokenizer::parse_str;
+
+    #[test]
+    pub fn test_diff() {
+        let from =
+            parse_str("{\"a\": 1, \"b\": [1, 2, 3], \"c\": {\"d\": true}, \"e/f\": null}").unwrap();
+        let to =
+            parse_str("{\"a\": 2, \"b\": [1, 5], \"c\": {\"d\": true, \"g\": \"x\"}}").unwrap();
+
+        assert_eq!(
+            vec![
+                Change::Changed {
+                    pointer: "/a".to_string(),
+                    from: JsonValue::Number(1.0),
+                    to: JsonValue::Number(2.0),
+                },
+                Change::Changed {
+                    pointer: "/b/1".to_string(),
+                    from: JsonValue::Number(2.0),
+                    to: JsonValue::Number(5.0),
+                },
+                Change::Removed {
+                    pointer: "/b/2".to_string(),
+                    value: JsonValue::Number(3.0),
+                },
+                Change::Added {
+                    pointer: "/c/g".to_string(),
+                    value: JsonValue::String("x".to_string()),
+                },
+                Change::Removed {
+                    pointer: "/e~1f".to_string(),
+                    value: JsonValue::NullValue,
+                },
+            ],
+            diff(&from, &to)
+        );
+    }
+
+    #[test]
+    pub fn test_diff_equal() {
+        let value = parse_str("{\"pairs\": [{\"x0\": 1.5}]}").unwrap();
+
+        assert_eq!(Vec::<Change>::new(), diff(&value, &value.clone()));
+    }
+
+    #[rstest]
+    #[case(1.0, 1.05, 0.1, 0.0, 0)]
+    #[case(1.0, 1.2, 0.1, 0.0, 1)]
+    #[case(1000.0, 1001.0, 0.0, 0.01, 0)]
+    #[case(1.0, 1.1, 0.0, 0.01, 1)]
+    pub fn test_diff_with_tolerance(
+        #[case] from: f64,
+        #[case] to: f64,
+        #[case] absolute: f64,
+        #[case] relative: f64,
+        #[case] expected_changes: usize,
+    ) {
+        let tolerance = Tolerance { absolute, relative };
+
+        assert_eq!(
+            expected_changes,
+            diff_with_tolerance(&JsonValue::Number(from), &JsonValue::Number(to), &tolerance).len()
+        );
+    }
+
+    #[test]
+    pub fn test_render_text() {
+        let from = parse_str("{\"a\": 1, \"b\": [1]}").unwrap();
+        let to = parse_str("{\"a\": \"1\", \"b\": [1, true]}").unwrap();
+
+        assert_eq!(
+            "~ /a: 1.0 -> \"1\"\n+ /b/1: true\n",
+            render_text(&diff(&from, &to))
+        );
+    }
+
+    #[rstest]
+    #[case("{\"a\": [1, 2, 3, 4], \"b\": 1}", "{\"a\": [0], \"c\": {}}")]
+    #[case("[1, {\"x\": 2}]", "[1, {\"x\": 3}, [4]]")]
+    #[case("{\"a\": 1}", "[1]")]
+    pub fn test_diff_to_patch(#[case] from: &str, #[case] to: &str) {
+        let mut from = parse_str(from).unwrap();
+        let to = parse_str(to).unwrap();
+
+        let patch = to_patch(&diff(&from, &to));
+        apply_patch(&mut from, &patch).unwrap();
+
+        assert_eq!(to, from);
+    }
+
+    #[test]
+    pub fn test_diff_to_patch_json() {
+        let from = parse_str("{\"a\": 1}").unwrap();
+        let to = parse_str("{}").unwrap();
+
+        assert_eq!(
+            parse_str("[{\"op\": \"remove\", \"path\": \"/a\"}]").unwrap(),
+            patch_to_json(&to_patch(&diff(&from, &to)))
+        );
+    }
+}
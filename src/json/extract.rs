@@ -0,0 +1,326 @@
+use std::io::BufRead;
+
+use super::{
+    de::{unexpected, Error},
+    lazy::skip_rest_using,
+    lexer::{LexedToken, Lexer, Token},
+    path::{parse_path, PathError, Segment, Selector},
+};
+
+// Every path is one bit of a u64 while the document is walked, so telling
+// which paths are still possible below a value never allocates.
+const MAX_PATHS: usize = 64;
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Scalar<'a> {
+    String(&'a str),
+    Number(f64),
+    Bool(bool),
+    Null,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum ExtractPathError {
+    Path(PathError),
+    Unsupported(String),
+    TooManyPaths,
+}
+
+#[derive(PartialEq, Debug)]
+enum Step {
+    Key(String),
+    Index(usize),
+    Any,
+}
+
+impl Step {
+    fn matches_key(&self, key: &str) -> bool {
+        match self {
+            Step::Key(expected) => expected == key,
+            Step::Index(_) => false,
+            Step::Any => true,
+        }
+    }
+
+    fn matches_index(&self, index: usize) -> bool {
+        match self {
+            Step::Key(_) => false,
+            Step::Index(expected) => *expected == index,
+            Step::Any => true,
+        }
+    }
+}
+
+// Only plain child names, indexes and wildcards can be decided while reading
+// forward. `pairs[*].x0` is accepted as a shorthand for `$.pairs[*].x0`.
+fn parse_steps(expression: &str) -> Result<Vec<Step>, ExtractPathError> {
+    let expression = match expression.chars().next() {
+        Some('$') => expression.to_string(),
+        Some('[') => format!("${}", expression),
+        _ => format!("$.{}", expression),
+    };
+    let path = parse_path(&expression).map_err(ExtractPathError::Path)?;
+    path.segments
+        .into_iter()
+        .map(|segment| match segment {
+            Segment {
+                descendant: false,
+                selector,
+            } => match selector {
+                Selector::Child(key) => Ok(Step::Key(key)),
+                Selector::Wildcard => Ok(Step::Any),
+                Selector::Index(index) if index >= 0 => Ok(Step::Index(index as usize)),
+                _ => Err(ExtractPathError::Unsupported(expression.clone())),
+            },
+            _ => Err(ExtractPathError::Unsupported(expression.clone())),
+        })
+        .collect()
+}
+
+struct Frame {
+    close: Token,
+    index: usize,
+    alive: u64,
+}
+
+// Reads a document once from start to end and reports the scalars found at
+// any of its paths. Subtrees no path can reach are skipped, still checking
+// that they are well formed, and nothing is allocated per value.
+pub struct Extractor {
+    paths: Vec<Vec<Step>>,
+}
+
+impl Extractor {
+    pub fn new(expressions: &[&str]) -> Result<Extractor, ExtractPathError> {
+        if expressions.len() > MAX_PATHS {
+            return Err(ExtractPathError::TooManyPaths);
+        }
+        Ok(Extractor {
+            paths: expressions
+                .iter()
+                .map(|expression| parse_steps(expression))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    fn matching<F>(&self, alive: u64, depth: usize, matches: F) -> u64
+    where
+        F: Fn(&Step) -> bool,
+    {
+        let mut result = 0;
+        for (i, steps) in self.paths.iter().enumerate() {
+            if alive & (1 << i) != 0 && steps.get(depth).is_some_and(&matches) {
+                result |= 1 << i;
+            }
+        }
+        result
+    }
+
+    // Works out which paths continue into the next member or element of the
+    // innermost container and returns them with the first token of its value.
+    fn enter<R>(
+        &self,
+        lexer: &mut Lexer<R>,
+        frames: &[Frame],
+        first: LexedToken,
+    ) -> Result<(u64, LexedToken), Error>
+    where
+        R: BufRead,
+    {
+        let depth = frames.len() - 1;
+        let frame = &frames[depth];
+        if frame.close != Token::EndObject {
+            let alive = self.matching(frame.alive, depth, |step| step.matches_index(frame.index));
+            return Ok((alive, first));
+        }
+        if !matches!(first.token, Token::String(_)) {
+            return Err(unexpected(&first, "string"));
+        }
+        let alive = self.matching(frame.alive, depth, |step| {
+            step.matches_key(lexer.last_string())
+        });
+        let colon = lexer.next_token_reusing()?;
+        if colon.token != Token::Colon {
+            return Err(unexpected(&colon, "`:`"));
+        }
+        Ok((alive, lexer.next_token_reusing()?))
+    }
+
+    fn emit<F>(&self, alive: u64, depth: usize, scalar: Scalar, callback: &mut F)
+    where
+        F: FnMut(usize, Scalar),
+    {
+        for (i, steps) in self.paths.iter().enumerate() {
+            if alive & (1 << i) != 0 && steps.len() == depth {
+                callback(i, scalar);
+            }
+        }
+    }
+
+    // Calls back with the index of the matching path and the scalar, in
+    // document order. Containers found at the end of a path are not reported.
+    pub fn run<R, F>(&self, reader: R, mut callback: F) -> Result<(), Error>
+    where
+        R: BufRead,
+        F: FnMut(usize, Scalar),
+    {
+        let mut lexer = Lexer::new(reader);
+        let mut frames: Vec<Frame> = Vec::new();
+        // Reused by every skipped subtree.
+        let mut closers = Vec::new();
+        let mut alive = u64::MAX
+            .checked_shr((MAX_PATHS - self.paths.len()) as u32)
+            .unwrap_or(0);
+        let mut token = lexer.next_token_reusing()?;
+        loop {
+            let depth = frames.len();
+            match token.token {
+                Token::BeginArray | Token::BeginObject if alive == 0 => {
+                    skip_rest_using(&mut lexer, token, &mut closers)?;
+                }
+                Token::BeginArray | Token::BeginObject => {
+                    let close = match token.token {
+                        Token::BeginArray => Token::EndArray,
+                        _ => Token::EndObject,
+                    };
+                    let first = lexer.next_token_reusing()?;
+                    if first.token != close {
+                        frames.push(Frame {
+                            close,
+                            index: 0,
+                            alive,
+                        });
+                        (alive, token) = self.enter(&mut lexer, &frames, first)?;
+                        continue;
+                    }
+                }
+                Token::String(_) => self.emit(
+                    alive,
+                    depth,
+                    Scalar::String(lexer.last_string()),
+                    &mut callback,
+                ),
+                Token::Number(number) => {
+                    self.emit(alive, depth, Scalar::Number(number), &mut callback)
+                }
+                Token::TrueValue => self.emit(alive, depth, Scalar::Bool(true), &mut callback),
+                Token::FalseValue => self.emit(alive, depth, Scalar::Bool(false), &mut callback),
+                Token::NullValue => self.emit(alive, depth, Scalar::Null, &mut callback),
+                _ => return Err(unexpected(&token, "value")),
+            }
+            loop {
+                let Some(frame) = frames.last_mut() else {
+                    let end = lexer.next_token_reusing()?;
+                    if end.token != Token::EndOfInput {
+                        return Err(unexpected(&end, "end of input"));
+                    }
+                    return Ok(());
+                };
+                let separator = lexer.next_token_reusing()?;
+                if separator.token == frame.close {
+                    frames.pop();
+                    continue;
+                }
+                if separator.token != Token::Comma {
+                    return Err(unexpected(&separator, &format!("`,` or {}", frame.close)));
+                }
+                frame.index += 1;
+                let first = lexer.next_token_reusing()?;
+                (alive, token) = self.enter(&mut lexer, &frames, first)?;
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::json::extract::*;
+
+    const DOCUMENT: &str = "{\"name\": \"run\", \"pairs\": [{\"x0\": 1, \"y0\": 2, \"extra\": [{\"x0\": 9}]}, {\"y0\": 4, \"x0\": 3}], \"x0\": 5}";
+
+    fn extract(expressions: &[&str], input: &str) -> Result<Vec<(usize, String)>, Error> {
+        let mut found = Vec::new();
+        Extractor::new(expressions)
+            .unwrap()
+            .run(input.as_bytes(), |i, scalar| {
+                found.push((i, format!("{:?}", scalar)))
+            })?;
+        Ok(found)
+    }
+
+    #[test]
+    pub fn test_extract_columns() {
+        assert_eq!(
+            vec![
+                (0, "Number(1.0)".to_string()),
+                (1, "Number(2.0)".to_string()),
+                (1, "Number(4.0)".to_string()),
+                (0, "Number(3.0)".to_string()),
+            ],
+            extract(&["pairs[*].x0", "$.pairs[*].y0"], DOCUMENT).unwrap()
+        );
+    }
+
+    #[rstest]
+    #[case("pairs[1].x0", vec!["Number(3.0)"])]
+    #[case("name", vec!["String(\"run\")"])]
+    #[case("['name']", vec!["String(\"run\")"])]
+    #[case("*", vec!["String(\"run\")", "Number(5.0)"])]
+    #[case("pairs[*].extra[0].x0", vec!["Number(9.0)"])]
+    #[case("pairs", vec![])]
+    #[case("name.first", vec![])]
+    #[case("pairs[2].x0", vec![])]
+    pub fn test_extract_single_path(#[case] expression: &str, #[case] expected: Vec<&str>) {
+        let found: Vec<String> = extract(&[expression], DOCUMENT)
+            .unwrap()
+            .into_iter()
+            .map(|(_, scalar)| scalar)
+            .collect();
+
+        assert_eq!(expected, found);
+    }
+
+    #[test]
+    pub fn test_extract_root_scalar() {
+        assert_eq!(
+            vec![(0, "Bool(true)".to_string())],
+            extract(&["$"], " true ").unwrap()
+        );
+    }
+
+    #[rstest]
+    #[case(
+        "{\"a\": [1, 2 3], \"x0\": 1}",
+        "expected `,` or `]` but found number at line 1 column 13 (byte 12)"
+    )]
+    #[case(
+        "{\"x0\": 1, \"a\": {\"b\" 1}}",
+        "expected `:` but found number at line 1 column 21 (byte 20)"
+    )]
+    #[case(
+        "{\"x0\": 1} 2",
+        "expected end of input but found number at line 1 column 11 (byte 10)"
+    )]
+    #[case(
+        "{\"x0\": 1,}",
+        "expected string but found `}` at line 1 column 10 (byte 9)"
+    )]
+    pub fn test_extract_checks_whole_document(#[case] input: &str, #[case] message: &str) {
+        assert_eq!(message, extract(&["x0"], input).unwrap_err().to_string());
+    }
+
+    #[rstest]
+    #[case("$..x0")]
+    #[case("pairs[-1]")]
+    #[case("pairs[0:2]")]
+    #[case("pairs[?(@.x0)]")]
+    pub fn test_extract_unsupported_paths(#[case] expression: &str) {
+        assert!(matches!(
+            Extractor::new(&[expression]),
+            Err(ExtractPathError::Unsupported(_))
+        ));
+    }
+}
@@ -0,0 +1,226 @@
+use std::{fmt, mem};
+
+use super::tokenizer::JsonValue;
+
+// Rows of `Footprint::by_kind`, named as `schema::type_name` names them.
+const KINDS: [&str; 6] = ["object", "array", "string", "number", "boolean", "null"];
+
+fn kind_index(value: &JsonValue) -> usize {
+    match value {
+        JsonValue::Object(_) => 0,
+        JsonValue::Array(_) => 1,
+        JsonValue::String(_) => 2,
+        JsonValue::Number(_) => 3,
+        JsonValue::TrueValue | JsonValue::FalseValue => 4,
+        JsonValue::NullValue => 5,
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Default, Clone, Copy)]
+pub struct Usage {
+    pub count: u64,
+    pub bytes: u64,
+}
+
+impl Usage {
+    fn add(&mut self, bytes: u64) {
+        self.count += 1;
+        self.bytes += bytes;
+    }
+}
+
+// Bytes used by a parsed document. Each node is charged for its own
+// JsonValue slot and for the heap it owns apart from its children's slots:
+// string buffers, spare vector capacity, and hash tables with their keys. So
+// the rows of either summary add up to `total`.
+#[derive(PartialEq, Eq, Debug)]
+pub struct Footprint {
+    pub total: u64,
+    pub by_kind: [Usage; 6],
+    pub by_depth: Vec<Usage>,
+}
+
+// What hashbrown allocates for a table that can hold `capacity` entries: a
+// power of two buckets of entries, plus one control byte per bucket and a
+// trailing group of them. Alignment padding is left out.
+fn table_bytes(capacity: usize, entry_size: usize) -> usize {
+    if capacity == 0 {
+        return 0;
+    }
+    let buckets = if capacity < 8 {
+        capacity + 1
+    } else {
+        (capacity * 8 / 7).next_power_of_two()
+    };
+    buckets * (entry_size + 1) + 16
+}
+
+// Heap owned by the value itself, leaving out its children's slots.
+fn own_heap_bytes(value: &JsonValue) -> usize {
+    let slot = mem::size_of::<JsonValue>();
+    match value {
+        JsonValue::Object(members) => {
+            let entry_size = mem::size_of::<(String, JsonValue)>();
+            let keys: usize = members.keys().map(String::capacity).sum();
+            table_bytes(members.capacity(), entry_size) - members.len() * slot + keys
+        }
+        JsonValue::Array(values) => (values.capacity() - values.len()) * slot,
+        JsonValue::String(str) => str.capacity(),
+        _ => 0,
+    }
+}
+
+fn visit(value: &JsonValue, depth: usize, footprint: &mut Footprint) {
+    let bytes = (mem::size_of::<JsonValue>() + own_heap_bytes(value)) as u64;
+    footprint.total += bytes;
+    footprint.by_kind[kind_index(value)].add(bytes);
+    if footprint.by_depth.len() == depth {
+        footprint.by_depth.push(Usage::default());
+    }
+    footprint.by_depth[depth].add(bytes);
+
+    match value {
+        JsonValue::Object(members) => {
+            for member in members.values() {
+                visit(member, depth + 1, footprint);
+            }
+        }
+        JsonValue::Array(values) => {
+            for element in values {
+                visit(element, depth + 1, footprint);
+            }
+        }
+        _ => {}
+    }
+}
+
+// Deep size estimate of `value`, including the slot of the root value.
+pub fn footprint(value: &JsonValue) -> Footprint {
+    let mut footprint = Footprint {
+        total: 0,
+        by_kind: [Usage::default(); 6],
+        by_depth: Vec::new(),
+    };
+    visit(value, 0, &mut footprint);
+    footprint
+}
+
+impl fmt::Display for Footprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "total\t{} bytes", self.total)?;
+        for (kind, usage) in KINDS.iter().zip(self.by_kind) {
+            if usage.count > 0 {
+                writeln!(f, "{}\tcount={} bytes={}", kind, usage.count, usage.bytes)?;
+            }
+        }
+        for (depth, usage) in self.by_depth.iter().enumerate() {
+            writeln!(
+                f,
+                "depth {}\tcount={} bytes={}",
+                depth, usage.count, usage.bytes
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use rstest::rstest;
+
+    use crate::json::footprint::*;
+
+    const SLOT: u64 = mem::size_of::<JsonValue>() as u64;
+
+    #[rstest]
+    #[case(JsonValue::NullValue, SLOT)]
+    #[case(JsonValue::String(String::from("abc")), SLOT + 3)]
+    #[case(JsonValue::Array(Vec::with_capacity(4)), SLOT * 5)]
+    #[case(JsonValue::Array(vec![JsonValue::Number(1.0), JsonValue::TrueValue]), SLOT * 3)]
+    #[case(JsonValue::Object(HashMap::new()), SLOT)]
+    pub fn test_footprint_total(#[case] value: JsonValue, #[case] expected: u64) {
+        assert_eq!(expected, footprint(&value).total);
+    }
+
+    #[test]
+    pub fn test_footprint_counts_object_keys() {
+        let mut short = HashMap::new();
+        short.insert(String::from("a"), JsonValue::NullValue);
+        let mut long = HashMap::new();
+        long.insert(String::from("abcdefgh"), JsonValue::NullValue);
+
+        assert_eq!(
+            7,
+            footprint(&JsonValue::Object(long)).total - footprint(&JsonValue::Object(short)).total
+        );
+    }
+
+    #[test]
+    pub fn test_footprint_summary() {
+        let value = JsonValue::Array(vec![
+            JsonValue::Array(vec![JsonValue::String(String::from("ab"))]),
+            JsonValue::FalseValue,
+            JsonValue::NullValue,
+        ]);
+
+        let footprint = footprint(&value);
+
+        assert_eq!(SLOT * 5 + 2, footprint.total);
+        assert_eq!(
+            [
+                Usage { count: 0, bytes: 0 },
+                Usage {
+                    count: 2,
+                    bytes: SLOT * 2
+                },
+                Usage {
+                    count: 1,
+                    bytes: SLOT + 2
+                },
+                Usage { count: 0, bytes: 0 },
+                Usage {
+                    count: 1,
+                    bytes: SLOT
+                },
+                Usage {
+                    count: 1,
+                    bytes: SLOT
+                },
+            ],
+            footprint.by_kind
+        );
+        assert_eq!(
+            vec![
+                Usage {
+                    count: 1,
+                    bytes: SLOT
+                },
+                Usage {
+                    count: 3,
+                    bytes: SLOT * 3
+                },
+                Usage {
+                    count: 1,
+                    bytes: SLOT + 2
+                },
+            ],
+            footprint.by_depth
+        );
+        assert_eq!(
+            format!(
+                "total\t{} bytes\narray\tcount=2 bytes={}\nstring\tcount=1 bytes={}\nboolean\tcount=1 bytes={}\nnull\tcount=1 bytes={}\ndepth 0\tcount=1 bytes={}\ndepth 1\tcount=3 bytes={}\ndepth 2\tcount=1 bytes={}\n",
+                SLOT * 5 + 2,
+                SLOT * 2,
+                SLOT + 2,
+                SLOT,
+                SLOT,
+                SLOT,
+                SLOT * 3,
+                SLOT + 2
+            ),
+            footprint.to_string()
+        );
+    }
+}
@@ -0,0 +1,431 @@
+use std::{collections::HashMap, fmt};
+
+use super::tokenizer::JsonValue;
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum FromJsonErrorKind {
+    Expected(&'static str),
+    MissingField,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct FromJsonError {
+    pub path: Vec<PathSegment>,
+    pub kind: FromJsonErrorKind,
+}
+
+impl FromJsonError {
+    pub fn expected(expected: &'static str) -> FromJsonError {
+        FromJsonError {
+            path: vec![],
+            kind: FromJsonErrorKind::Expected(expected),
+        }
+    }
+
+    pub fn within(mut self, segment: PathSegment) -> FromJsonError {
+        self.path.insert(0, segment);
+        self
+    }
+}
+
+// Renders as "pairs[17].y0: expected number".
+impl fmt::Display for FromJsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.path.iter().enumerate() {
+            match segment {
+                PathSegment::Key(key) if i == 0 => write!(f, "{}", key)?,
+                PathSegment::Key(key) => write!(f, ".{}", key)?,
+                PathSegment::Index(index) => write!(f, "[{}]", index)?,
+            }
+        }
+        if !self.path.is_empty() {
+            write!(f, ": ")?;
+        }
+        match self.kind {
+            FromJsonErrorKind::Expected(expected) => write!(f, "expected {}", expected),
+            FromJsonErrorKind::MissingField => write!(f, "missing field"),
+        }
+    }
+}
+
+#[allow(dead_code)]
+pub trait FromJson: Sized {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError>;
+
+    // The value used when an object member is absent. Only Option has one.
+    fn from_missing() -> Option<Self> {
+        None
+    }
+}
+
+#[allow(dead_code)]
+pub trait ToJson {
+    fn to_json(&self) -> JsonValue;
+}
+
+pub fn field<T>(map: &HashMap<String, JsonValue>, name: &str) -> Result<T, FromJsonError>
+where
+    T: FromJson,
+{
+    let within = |e: FromJsonError| e.within(PathSegment::Key(name.to_string()));
+    match map.get(name) {
+        Some(value) => T::from_json(value).map_err(within),
+        None => T::from_missing().ok_or_else(|| {
+            within(FromJsonError {
+                path: vec![],
+                kind: FromJsonErrorKind::MissingField,
+            })
+        }),
+    }
+}
+
+// Implements FromJson and ToJson for a struct whose fields all implement
+// them, mapping every field to the object member of the same name:
+//
+//     derive_json!(Pair { x0, x1, y0, y1 });
+macro_rules! derive_json {
+    ($name: ident { $($field: ident),* $(,)? }) => {
+        impl $crate::json::from_json::FromJson for $name {
+            fn from_json(
+                value: &$crate::json::tokenizer::JsonValue,
+            ) -> Result<Self, $crate::json::from_json::FromJsonError> {
+                let $crate::json::tokenizer::JsonValue::Object(map) = value else {
+                    return Err($crate::json::from_json::FromJsonError::expected("object"));
+                };
+                Ok($name {
+                    $($field: $crate::json::from_json::field(map, stringify!($field))?,)*
+                })
+            }
+        }
+
+        impl $crate::json::from_json::ToJson for $name {
+            fn to_json(&self) -> $crate::json::tokenizer::JsonValue {
+                $crate::json::tokenizer::JsonValue::Object(std::collections::HashMap::from([
+                    $((
+                        stringify!($field).to_string(),
+                        $crate::json::from_json::ToJson::to_json(&self.$field),
+                    ),)*
+                ]))
+            }
+        }
+    };
+}
+pub(crate) use derive_json;
+
+impl FromJson for f64 {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        value.as_f64().ok_or(FromJsonError::expected("number"))
+    }
+}
+
+impl FromJson for f32 {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        f64::from_json(value).map(|number| number as f32)
+    }
+}
+
+// `number` as a T, when it is a whole number in T's range. Whole f64s below
+// 2^127 convert to i128 exactly, larger ones saturate to a value no narrower
+// integer type accepts.
+pub fn exact_integer<T>(number: f64) -> Option<T>
+where
+    T: TryFrom<i128>,
+{
+    if number.fract() != 0.0 {
+        return None;
+    }
+    T::try_from(number as i128).ok()
+}
+
+macro_rules! integer_json {
+    ($($integer: ty),*) => {
+        $(
+            impl FromJson for $integer {
+                fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+                    exact_integer(f64::from_json(value)?)
+                        .ok_or(FromJsonError::expected(stringify!($integer)))
+                }
+            }
+
+            impl ToJson for $integer {
+                fn to_json(&self) -> JsonValue {
+                    JsonValue::Number(*self as f64)
+                }
+            }
+        )*
+    };
+}
+
+integer_json!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl FromJson for bool {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        value.as_bool().ok_or(FromJsonError::expected("boolean"))
+    }
+}
+
+impl FromJson for String {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        value
+            .as_str()
+            .map(String::from)
+            .ok_or(FromJsonError::expected("string"))
+    }
+}
+
+impl<T> FromJson for Option<T>
+where
+    T: FromJson,
+{
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        match value {
+            JsonValue::NullValue => Ok(None),
+            value => T::from_json(value).map(Some),
+        }
+    }
+
+    fn from_missing() -> Option<Self> {
+        Some(None)
+    }
+}
+
+impl<T> FromJson for Vec<T>
+where
+    T: FromJson,
+{
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        let items = value.as_array().ok_or(FromJsonError::expected("array"))?;
+        items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| T::from_json(item).map_err(|e| e.within(PathSegment::Index(i))))
+            .collect()
+    }
+}
+
+impl<T> FromJson for HashMap<String, T>
+where
+    T: FromJson,
+{
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        let map = value.as_object().ok_or(FromJsonError::expected("object"))?;
+        map.iter()
+            .map(|(key, value)| {
+                T::from_json(value)
+                    .map(|value| (key.clone(), value))
+                    .map_err(|e| e.within(PathSegment::Key(key.clone())))
+            })
+            .collect()
+    }
+}
+
+impl FromJson for JsonValue {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        Ok(value.clone())
+    }
+}
+
+impl ToJson for f64 {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Number(*self)
+    }
+}
+
+impl ToJson for f32 {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Number(*self as f64)
+    }
+}
+
+impl ToJson for bool {
+    fn to_json(&self) -> JsonValue {
+        if *self {
+            JsonValue::TrueValue
+        } else {
+            JsonValue::FalseValue
+        }
+    }
+}
+
+impl ToJson for String {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::String(self.clone())
+    }
+}
+
+impl ToJson for str {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::String(self.to_string())
+    }
+}
+
+impl<T> ToJson for Option<T>
+where
+    T: ToJson,
+{
+    fn to_json(&self) -> JsonValue {
+        match self {
+            Some(value) => value.to_json(),
+            None => JsonValue::NullValue,
+        }
+    }
+}
+
+impl<T> ToJson for Vec<T>
+where
+    T: ToJson,
+{
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Array(self.iter().map(ToJson::to_json).collect())
+    }
+}
+
+impl<T> ToJson for HashMap<String, T>
+where
+    T: ToJson,
+{
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Object(
+            self.iter()
+                .map(|(key, value)| (key.clone(), value.to_json()))
+                .collect(),
+        )
+    }
+}
+
+impl ToJson for JsonValue {
+    fn to_json(&self) -> JsonValue {
+        self.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::json::from_json::*;
+    use crate::json::tokenizer::parse_str;
+
+    #[derive(PartialEq, Debug)]
+    struct Point {
+        x: f64,
+        label: Option<String>,
+    }
+
+    derive_json!(Point { x, label });
+
+    #[derive(PartialEq, Debug)]
+    struct Shape {
+        points: Vec<Point>,
+        closed: bool,
+        tags: HashMap<String, u32>,
+    }
+
+    derive_json!(Shape {
+        points,
+        closed,
+        tags,
+    });
+
+    #[test]
+    pub fn test_from_json_struct() {
+        let value = parse_str(
+            "{\"points\": [{\"x\": 1.5}, {\"x\": 2, \"label\": \"b\"}], \"closed\": true, \"tags\": {\"a\": 3}}",
+        )
+        .unwrap();
+
+        assert_eq!(
+            Ok(Shape {
+                points: vec![
+                    Point {
+                        x: 1.5,
+                        label: None
+                    },
+                    Point {
+                        x: 2.0,
+                        label: Some("b".to_string())
+                    },
+                ],
+                closed: true,
+                tags: HashMap::from([("a".to_string(), 3)]),
+            }),
+            Shape::from_json(&value)
+        );
+    }
+
+    #[test]
+    pub fn test_to_json_round_trip() {
+        let value = parse_str(
+            "{\"points\": [{\"x\": 1.5, \"label\": null}], \"closed\": false, \"tags\": {}}",
+        )
+        .unwrap();
+
+        assert_eq!(value, Shape::from_json(&value).unwrap().to_json());
+    }
+
+    #[rstest]
+    #[case(
+        "{\"points\": [{\"x\": 1}, {\"x\": \"2\"}], \"closed\": true, \"tags\": {}}",
+        "points[1].x: expected number"
+    )]
+    #[case(
+        "{\"points\": [{\"label\": \"a\"}], \"closed\": true, \"tags\": {}}",
+        "points[0].x: missing field"
+    )]
+    #[case("{\"points\": [], \"tags\": {}}", "closed: missing field")]
+    #[case(
+        "{\"points\": [], \"closed\": true, \"tags\": {\"a\": 1.5}}",
+        "tags.a: expected u32"
+    )]
+    #[case("[]", "expected object")]
+    pub fn test_from_json_errors(#[case] input: &str, #[case] expected: &str) {
+        let error = Shape::from_json(&parse_str(input).unwrap()).unwrap_err();
+
+        assert_eq!(expected, error.to_string());
+    }
+
+    #[rstest]
+    #[case("300", false)]
+    #[case("-1", false)]
+    #[case("255", true)]
+    pub fn test_from_json_integer_range(#[case] input: &str, #[case] accepted: bool) {
+        assert_eq!(accepted, u8::from_json(&parse_str(input).unwrap()).is_ok());
+    }
+
+    #[rstest]
+    #[case(9223372036854775808.0, None, Some(9223372036854775808))]
+    #[case(
+        9223372036854774784.0,
+        Some(9223372036854774784),
+        Some(9223372036854774784)
+    )]
+    #[case(-9223372036854775808.0, Some(i64::MIN), None)]
+    #[case(18446744073709551616.0, None, None)]
+    #[case(18446744073709549568.0, None, Some(18446744073709549568))]
+    #[case(-1.0, Some(-1), None)]
+    pub fn test_from_json_64_bit_boundaries(
+        #[case] number: f64,
+        #[case] signed: Option<i64>,
+        #[case] unsigned: Option<u64>,
+    ) {
+        let value = JsonValue::Number(number);
+
+        assert_eq!(signed, i64::from_json(&value).ok());
+        assert_eq!(unsigned, u64::from_json(&value).ok());
+    }
+
+    #[rstest]
+    #[case(2147483647.0, Some(i32::MAX))]
+    #[case(2147483648.0, None)]
+    #[case(-2147483648.0, Some(i32::MIN))]
+    #[case(-2147483649.0, None)]
+    pub fn test_from_json_i32_boundaries(#[case] number: f64, #[case] expected: Option<i32>) {
+        assert_eq!(expected, i32::from_json(&JsonValue::Number(number)).ok());
+    }
+}
@@ -0,0 +1,389 @@
+use std::{
+    fs::{self, File, Metadata},
+    io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use serde::de::DeserializeOwned;
+
+use super::{
+    de::{self, unexpected, Error},
+    lazy::{expect_key, skip_rest_using, skip_value},
+    lexer::{Lexer, LexerError, ParseOptions, Position, Token},
+};
+
+// Sidecar layout, all integers little-endian u64 after the magic:
+//   "JIDX" version source_length source_mtime_nanos
+//   then one entry per element: start_offset start_line start_column end_offset
+// Entries have a fixed size so element i can be read with a single seek.
+const MAGIC: &[u8; 4] = b"JIDX";
+const VERSION: u64 = 1;
+const HEADER_SIZE: u64 = 4 + 8 * 3;
+const ENTRY_SIZE: u64 = 8 * 4;
+
+#[derive(PartialEq, Debug)]
+pub enum IndexError {
+    Io(io::ErrorKind),
+    Parse(Error),
+    MissingMember(String),
+    InvalidIndex,
+    StaleIndex,
+    OutOfBounds(u64),
+}
+
+impl From<io::Error> for IndexError {
+    fn from(error: io::Error) -> Self {
+        IndexError::Io(error.kind())
+    }
+}
+
+impl From<Error> for IndexError {
+    fn from(error: Error) -> Self {
+        IndexError::Parse(error)
+    }
+}
+
+impl From<LexerError> for IndexError {
+    fn from(error: LexerError) -> Self {
+        IndexError::Parse(error.into())
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct IndexEntry {
+    pub start: Position,
+    pub end: u64,
+}
+
+impl IndexEntry {
+    fn write_to<W>(&self, writer: &mut W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        for value in [
+            self.start.offset,
+            self.start.line,
+            self.start.column,
+            self.end,
+        ] {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    fn read_from<R>(reader: &mut R) -> io::Result<IndexEntry>
+    where
+        R: Read,
+    {
+        Ok(IndexEntry {
+            start: Position {
+                offset: read_u64(reader)?,
+                line: read_u64(reader)?,
+                column: read_u64(reader)?,
+            },
+            end: read_u64(reader)?,
+        })
+    }
+}
+
+fn read_u64<R>(reader: &mut R) -> io::Result<u64>
+where
+    R: Read,
+{
+    let mut buffer = [0; 8];
+    reader.read_exact(&mut buffer)?;
+    Ok(u64::from_le_bytes(buffer))
+}
+
+pub fn modified_nanos(metadata: &Metadata) -> u64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |duration| duration.as_nanos() as u64)
+}
+
+pub fn index_path(json_path: &Path) -> PathBuf {
+    let mut path = json_path.as_os_str().to_owned();
+    path.push(".idx");
+    PathBuf::from(path)
+}
+
+// Streams through the document and reports where each element of the array
+// starts and ends. With a member name the array is that member of the root
+// object, otherwise the root itself. Elements are skipped, not built, and
+// nothing after the closing bracket is read.
+pub fn scan_elements<R, F>(reader: R, member: Option<&str>, mut found: F) -> Result<(), IndexError>
+where
+    R: BufRead,
+    F: FnMut(IndexEntry) -> Result<(), IndexError>,
+{
+    let mut lexer = Lexer::new(reader);
+    if let Some(member) = member {
+        let token = lexer.next_token_reusing()?;
+        if token.token != Token::BeginObject {
+            return Err(unexpected(&token, "`{`").into());
+        }
+        loop {
+            if expect_key(&mut lexer)? == member {
+                break;
+            }
+            skip_value(&mut lexer)?;
+            let separator = lexer.next_token_reusing()?;
+            match separator.token {
+                Token::Comma => continue,
+                Token::EndObject => return Err(IndexError::MissingMember(member.to_string())),
+                _ => return Err(unexpected(&separator, "`,` or `}`").into()),
+            }
+        }
+    }
+    let token = lexer.next_token_reusing()?;
+    if token.token != Token::BeginArray {
+        return Err(unexpected(&token, "`[`").into());
+    }
+    let mut first = true;
+    let mut closers = Vec::new();
+    loop {
+        let token = lexer.next_token_reusing()?;
+        if first && token.token == Token::EndArray {
+            return Ok(());
+        }
+        first = false;
+        let start = token.start;
+        let end = skip_rest_using(&mut lexer, token, &mut closers)?;
+        found(IndexEntry {
+            start,
+            end: end.offset,
+        })?;
+        let separator = lexer.next_token_reusing()?;
+        match separator.token {
+            Token::Comma => continue,
+            Token::EndArray => return Ok(()),
+            _ => return Err(unexpected(&separator, "`,` or `]`").into()),
+        }
+    }
+}
+
+fn write_index(
+    json: File,
+    metadata: &Metadata,
+    member: Option<&str>,
+    index: &Path,
+) -> Result<u64, IndexError> {
+    let mut writer = BufWriter::new(File::create(index)?);
+    writer.write_all(MAGIC)?;
+    for value in [VERSION, metadata.len(), modified_nanos(metadata)] {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    let mut count = 0;
+    scan_elements(BufReader::new(json), member, |entry| {
+        count += 1;
+        Ok(entry.write_to(&mut writer)?)
+    })?;
+    writer.flush()?;
+    Ok(count)
+}
+
+// Writes `<json_path>.idx` and returns the number of elements indexed. The
+// index only replaces the previous one once the whole array has been
+// scanned, so a parse error never leaves a truncated index behind.
+pub fn build_index(json_path: &Path, member: Option<&str>) -> Result<u64, IndexError> {
+    let json = File::open(json_path)?;
+    let metadata = json.metadata()?;
+    let temporary = index_path(json_path).with_extension("idx.tmp");
+    match write_index(json, &metadata, member, &temporary) {
+        Ok(count) => {
+            fs::rename(temporary, index_path(json_path))?;
+            Ok(count)
+        }
+        Err(error) => {
+            let _ = fs::remove_file(temporary);
+            Err(error)
+        }
+    }
+}
+
+// Random access to the elements of an indexed array. Only the requested
+// entry of the index and the bytes of the requested element are read.
+pub struct IndexedArray {
+    json: File,
+    json_len: u64,
+    index: File,
+    len: u64,
+}
+
+impl IndexedArray {
+    pub fn open(json_path: &Path) -> Result<IndexedArray, IndexError> {
+        let json = File::open(json_path)?;
+        let mut index = File::open(index_path(json_path))?;
+        let mut magic = [0; 4];
+        index.read_exact(&mut magic)?;
+        if &magic != MAGIC || read_u64(&mut index)? != VERSION {
+            return Err(IndexError::InvalidIndex);
+        }
+        let metadata = json.metadata()?;
+        if read_u64(&mut index)? != metadata.len()
+            || read_u64(&mut index)? != modified_nanos(&metadata)
+        {
+            return Err(IndexError::StaleIndex);
+        }
+        let entries_size = index.metadata()?.len() - HEADER_SIZE;
+        let len = entries_size / ENTRY_SIZE;
+        if len * ENTRY_SIZE != entries_size {
+            return Err(IndexError::InvalidIndex);
+        }
+        Ok(IndexedArray {
+            json,
+            json_len: metadata.len(),
+            index,
+            len,
+        })
+    }
+
+    #[allow(dead_code)]
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn entry(&mut self, i: u64) -> Result<IndexEntry, IndexError> {
+        if i >= self.len {
+            return Err(IndexError::OutOfBounds(i));
+        }
+        self.index
+            .seek(SeekFrom::Start(HEADER_SIZE + i * ENTRY_SIZE))?;
+        let entry = IndexEntry::read_from(&mut self.index)?;
+        // A damaged or edited index must not make raw underflow or allocate
+        // more than the file holds.
+        if entry.start.offset > entry.end || entry.end > self.json_len {
+            return Err(IndexError::InvalidIndex);
+        }
+        Ok(entry)
+    }
+
+    pub fn raw(&mut self, i: u64) -> Result<Vec<u8>, IndexError> {
+        let entry = self.entry(i)?;
+        let mut buffer = vec![0; (entry.end - entry.start.offset) as usize];
+        self.json.seek(SeekFrom::Start(entry.start.offset))?;
+        self.json.read_exact(&mut buffer)?;
+        Ok(buffer)
+    }
+
+    // Parses element i into a JsonValue or any deserializable type. Error
+    // positions point into the JSON file, not into the element.
+    pub fn get<T>(&mut self, i: u64) -> Result<T, IndexError>
+    where
+        T: DeserializeOwned,
+    {
+        let start = self.entry(i)?.start;
+        let raw = self.raw(i)?;
+        Ok(de::from_lexer(Lexer::with_position(
+            &raw[..],
+            ParseOptions::default(),
+            start,
+        ))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use rstest::rstest;
+
+    use crate::json::index::*;
+    use crate::json::tokenizer::{parse_str, JsonValue};
+
+    fn scan(input: &str, member: Option<&str>) -> Result<Vec<(u64, u64)>, IndexError> {
+        let mut found = Vec::new();
+        scan_elements(input.as_bytes(), member, |entry| {
+            found.push((entry.start.offset, entry.end));
+            Ok(())
+        })?;
+        Ok(found)
+    }
+
+    #[rstest]
+    #[case("[1, \"a,]\", [2, 3], {\"b\": []}]", None, vec![(1, 2), (4, 9), (11, 17), (19, 28)])]
+    #[case("[ ]", None, vec![])]
+    #[case("{\"name\": [0], \"pairs\": [ 7 ,8]} trailing", Some("pairs"), vec![(25, 26), (28, 29)])]
+    pub fn test_scan_elements(
+        #[case] input: &str,
+        #[case] member: Option<&str>,
+        #[case] expected: Vec<(u64, u64)>,
+    ) {
+        assert_eq!(Ok(expected), scan(input, member));
+    }
+
+    #[rstest]
+    #[case("{\"name\": 1}", Some("pairs"))]
+    #[case("{\"pairs\": {}}", Some("pairs"))]
+    #[case("[1, [2,], 3]", None)]
+    #[case("[1 2]", None)]
+    pub fn test_scan_elements_errors(#[case] input: &str, #[case] member: Option<&str>) {
+        assert!(scan(input, member).is_err());
+    }
+
+    #[test]
+    pub fn test_build_index_and_read_elements() {
+        let json_path = env::temp_dir().join(format!("index-test-{}.json", process::id()));
+        fs::write(
+            &json_path,
+            "{\"pairs\": [\n  {\"x0\": 1, \"y0\": 2},\n  {\"x0\": 3, \"y0\": true}\n]}",
+        )
+        .unwrap();
+
+        assert_eq!(Ok(2), build_index(&json_path, Some("pairs")));
+        let mut array = IndexedArray::open(&json_path).unwrap();
+
+        assert_eq!(2, array.len());
+        assert_eq!(
+            parse_str("{\"x0\": 1, \"y0\": 2}").unwrap(),
+            array.get::<JsonValue>(0).unwrap()
+        );
+        assert_eq!(
+            "invalid type: boolean `true`, expected f64 at line 3 column 19 (byte 52)",
+            match array.get::<std::collections::HashMap<String, f64>>(1) {
+                Err(IndexError::Parse(e)) => e.to_string(),
+                other => panic!("{:?}", other),
+            }
+        );
+        assert_eq!(Err(IndexError::OutOfBounds(2)), array.entry(2).map(|_| ()));
+
+        let mut index = fs::OpenOptions::new()
+            .write(true)
+            .open(index_path(&json_path))
+            .unwrap();
+        for end in [0, u64::MAX] {
+            index
+                .seek(SeekFrom::Start(HEADER_SIZE + ENTRY_SIZE - 8))
+                .unwrap();
+            index.write_all(&end.to_le_bytes()).unwrap();
+            assert_eq!(Err(IndexError::InvalidIndex), array.raw(0));
+        }
+
+        fs::write(&json_path, "{\"pairs\": []}").unwrap();
+        assert_eq!(
+            Some(IndexError::StaleIndex),
+            IndexedArray::open(&json_path).err()
+        );
+
+        fs::remove_file(index_path(&json_path)).unwrap();
+        fs::remove_file(&json_path).unwrap();
+    }
+
+    #[test]
+    pub fn test_build_index_keeps_nothing_on_parse_error() {
+        let json_path = env::temp_dir().join(format!("index-error-test-{}.json", process::id()));
+        fs::write(&json_path, "[1, 2, 3, {\"x0\": ]").unwrap();
+
+        let result = build_index(&json_path, None);
+        let index_exists = index_path(&json_path).exists();
+        let temporary_exists = index_path(&json_path).with_extension("idx.tmp").exists();
+        fs::remove_file(&json_path).unwrap();
+
+        assert!(matches!(result, Err(IndexError::Parse(_))));
+        assert!(!index_exists);
+        assert!(!temporary_exists);
+    }
+}
@@ -0,0 +1,429 @@
+use std::io::BufRead;
+
+use serde::de::DeserializeOwned;
+
+use super::{
+    de::{self, unexpected, Error},
+    lexer::{LexedToken, Lexer, ParseOptions, Position, Token},
+};
+
+pub fn expect_key<R>(lexer: &mut Lexer<R>) -> Result<&str, Error>
+where
+    R: BufRead,
+{
+    let token = lexer.next_token_reusing()?;
+    if !matches!(token.token, Token::String(_)) {
+        return Err(unexpected(&token, "string"));
+    }
+    expect_colon(lexer)?;
+    Ok(lexer.last_string())
+}
+
+fn expect_colon<R>(lexer: &mut Lexer<R>) -> Result<(), Error>
+where
+    R: BufRead,
+{
+    let colon = lexer.next_token_reusing()?;
+    if colon.token != Token::Colon {
+        return Err(unexpected(&colon, "`:`"));
+    }
+    Ok(())
+}
+
+// Reads past one complete value and returns where it ends.
+pub fn skip_value<R>(lexer: &mut Lexer<R>) -> Result<Position, Error>
+where
+    R: BufRead,
+{
+    let token = lexer.next_token_reusing()?;
+    skip_rest(lexer, token)
+}
+
+// Finishes skipping a value whose first token was already read. Containers
+// are matched with an explicit stack instead of recursion, and every
+// separator is checked on the way, so a skipped subtree is still known to be
+// well formed. Strings are not copied out of the lexer.
+pub fn skip_rest<R>(lexer: &mut Lexer<R>, first: LexedToken) -> Result<Position, Error>
+where
+    R: BufRead,
+{
+    skip_rest_using(lexer, first, &mut Vec::new())
+}
+
+// Same as skip_rest with a caller-owned stack, so skipping many values one
+// after another does not allocate for each of them.
+pub fn skip_rest_using<R>(
+    lexer: &mut Lexer<R>,
+    first: LexedToken,
+    closers: &mut Vec<Token>,
+) -> Result<Position, Error>
+where
+    R: BufRead,
+{
+    closers.clear();
+    let mut token = first;
+    loop {
+        let mut end = match token.token {
+            Token::BeginArray | Token::BeginObject => {
+                let close = match token.token {
+                    Token::BeginArray => Token::EndArray,
+                    _ => Token::EndObject,
+                };
+                let next = lexer.next_token_reusing()?;
+                if next.token == close {
+                    next.end
+                } else {
+                    if close == Token::EndObject {
+                        if !matches!(next.token, Token::String(_)) {
+                            return Err(unexpected(&next, &format!("string or {}", close)));
+                        }
+                        expect_colon(lexer)?;
+                        token = lexer.next_token_reusing()?;
+                    } else {
+                        token = next;
+                    }
+                    closers.push(close);
+                    continue;
+                }
+            }
+            Token::String(_)
+            | Token::Number(_)
+            | Token::TrueValue
+            | Token::FalseValue
+            | Token::NullValue => token.end,
+            _ => return Err(unexpected(&token, "value")),
+        };
+        loop {
+            let Some(closer) = closers.last() else {
+                return Ok(end);
+            };
+            let separator = lexer.next_token_reusing()?;
+            if separator.token == *closer {
+                end = separator.end;
+                closers.pop();
+                continue;
+            }
+            if separator.token != Token::Comma {
+                return Err(unexpected(&separator, &format!("`,` or {}", closer)));
+            }
+            if *closer == Token::EndObject {
+                expect_key(lexer)?;
+            }
+            token = lexer.next_token_reusing()?;
+            break;
+        }
+    }
+}
+
+// A document held in memory that is only tokenized as far as a caller walks
+// into it. Asking for `pairs` skips over every other member of the root and
+// asking for its length skips over each pair without building any of them.
+#[allow(dead_code)]
+pub struct LazyDocument<'a> {
+    input: &'a [u8],
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LazyValue<'a> {
+    input: &'a [u8],
+    start: Position,
+    end: Option<Position>,
+}
+
+#[allow(dead_code)]
+impl<'a> LazyDocument<'a> {
+    pub fn new(input: &'a [u8]) -> LazyDocument<'a> {
+        LazyDocument { input }
+    }
+
+    pub fn root(&self) -> Result<LazyValue<'a>, Error> {
+        let mut lexer = Lexer::new(self.input);
+        Ok(LazyValue {
+            input: self.input,
+            start: lexer.peek()?.start,
+            end: None,
+        })
+    }
+
+    // Walks the whole document once, the lazy accessors only check the parts
+    // they pass over.
+    pub fn validate(&self) -> Result<(), Error> {
+        let mut lexer = Lexer::new(self.input);
+        skip_value(&mut lexer)?;
+        let token = lexer.next_token()?;
+        if token.token != Token::EndOfInput {
+            return Err(unexpected(&token, "end of input"));
+        }
+        Ok(())
+    }
+}
+
+#[allow(dead_code)]
+impl<'a> LazyValue<'a> {
+    fn lexer(&self) -> Lexer<&'a [u8]> {
+        Lexer::with_position(
+            &self.input[self.start.offset as usize..],
+            ParseOptions::default(),
+            self.start,
+        )
+    }
+
+    pub fn start(&self) -> Position {
+        self.start
+    }
+
+    pub fn end(&self) -> Result<Position, Error> {
+        match self.end {
+            Some(end) => Ok(end),
+            None => skip_value(&mut self.lexer()),
+        }
+    }
+
+    pub fn raw(&self) -> Result<&'a [u8], Error> {
+        Ok(&self.input[self.start.offset as usize..self.end()?.offset as usize])
+    }
+
+    pub fn is_object(&self) -> bool {
+        self.input.get(self.start.offset as usize) == Some(&b'{')
+    }
+
+    pub fn is_array(&self) -> bool {
+        self.input.get(self.start.offset as usize) == Some(&b'[')
+    }
+
+    // Calls back with every member or element in order, skipping over each one
+    // after the callback returns. Stops early once the callback returns false.
+    fn walk<F>(&self, open: Token, close: Token, mut visit: F) -> Result<(), Error>
+    where
+        F: FnMut(Option<String>, LazyValue<'a>) -> bool,
+    {
+        let mut lexer = self.lexer();
+        let token = lexer.next_token()?;
+        if token.token != open {
+            return Err(unexpected(&token, &open.to_string()));
+        }
+        if lexer.peek()?.token == close {
+            return Ok(());
+        }
+        loop {
+            let key = match close {
+                Token::EndObject => Some(expect_key(&mut lexer)?.to_string()),
+                _ => None,
+            };
+            let start = lexer.peek()?.start;
+            let end = skip_value(&mut lexer)?;
+            let value = LazyValue {
+                input: self.input,
+                start,
+                end: Some(end),
+            };
+            if !visit(key, value) {
+                return Ok(());
+            }
+            let separator = lexer.next_token()?;
+            if separator.token == close {
+                return Ok(());
+            }
+            if separator.token != Token::Comma {
+                return Err(unexpected(&separator, &format!("`,` or {}", close)));
+            }
+        }
+    }
+
+    // Returns the first member with this key. Members after it are not read.
+    pub fn get(&self, key: &str) -> Result<Option<LazyValue<'a>>, Error> {
+        let mut found = None;
+        self.walk(Token::BeginObject, Token::EndObject, |member, value| {
+            if member.as_deref() == Some(key) {
+                found = Some(value);
+            }
+            found.is_none()
+        })?;
+        Ok(found)
+    }
+
+    pub fn index(&self, index: usize) -> Result<Option<LazyValue<'a>>, Error> {
+        let mut found = None;
+        let mut i = 0;
+        self.walk(Token::BeginArray, Token::EndArray, |_, value| {
+            if i == index {
+                found = Some(value);
+            }
+            i += 1;
+            found.is_none()
+        })?;
+        Ok(found)
+    }
+
+    // Number of elements of an array or members of an object.
+    pub fn len(&self) -> Result<usize, Error> {
+        let (open, close) = if self.is_object() {
+            (Token::BeginObject, Token::EndObject)
+        } else {
+            (Token::BeginArray, Token::EndArray)
+        };
+        let mut count = 0;
+        self.walk(open, close, |_, _| {
+            count += 1;
+            true
+        })?;
+        Ok(count)
+    }
+
+    pub fn members(&self) -> Result<Vec<(String, LazyValue<'a>)>, Error> {
+        let mut members = Vec::new();
+        self.walk(Token::BeginObject, Token::EndObject, |key, value| {
+            members.push((key.unwrap(), value));
+            true
+        })?;
+        Ok(members)
+    }
+
+    pub fn items(&self) -> Result<Vec<LazyValue<'a>>, Error> {
+        let mut items = Vec::new();
+        self.walk(Token::BeginArray, Token::EndArray, |_, value| {
+            items.push(value);
+            true
+        })?;
+        Ok(items)
+    }
+
+    // Fully parses this value, into a JsonValue or any other deserializable
+    // type. Errors keep their position in the whole document.
+    pub fn parse<T>(&self) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        let raw = self.raw()?;
+        de::from_lexer(Lexer::with_position(
+            raw,
+            ParseOptions::default(),
+            self.start,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::json::lazy::*;
+    use crate::json::tokenizer::{parse_str, JsonValue};
+
+    const DOCUMENT: &str = "{\"name\": \"run\", \"pairs\": [{\"x0\": 1, \"y0\": [2, {}]}, {\"x0\": 3}, []], \"last\": null}";
+
+    #[test]
+    pub fn test_lazy_get_and_len() {
+        let document = LazyDocument::new(DOCUMENT.as_bytes());
+        let pairs = document.root().unwrap().get("pairs").unwrap().unwrap();
+
+        assert!(pairs.is_array());
+        assert_eq!(3, pairs.len().unwrap());
+        assert_eq!(3, document.root().unwrap().len().unwrap());
+        assert_eq!(
+            None,
+            document
+                .root()
+                .unwrap()
+                .get("missing")
+                .unwrap()
+                .map(|v| v.start())
+        );
+    }
+
+    #[test]
+    pub fn test_lazy_index_and_parse() {
+        let document = LazyDocument::new(DOCUMENT.as_bytes());
+        let pairs = document.root().unwrap().get("pairs").unwrap().unwrap();
+        let second = pairs.index(1).unwrap().unwrap();
+
+        assert_eq!(b"{\"x0\": 3}", second.raw().unwrap());
+        assert_eq!(
+            parse_str("{\"x0\": 3}").unwrap(),
+            second.parse::<JsonValue>().unwrap()
+        );
+        assert_eq!(
+            3.0,
+            second.get("x0").unwrap().unwrap().parse::<f64>().unwrap()
+        );
+        assert!(pairs.index(3).unwrap().is_none());
+    }
+
+    #[test]
+    pub fn test_lazy_root_parse_matches_tokenizer() {
+        let document = LazyDocument::new(DOCUMENT.as_bytes());
+
+        assert_eq!(
+            parse_str(DOCUMENT).unwrap(),
+            document.root().unwrap().parse::<JsonValue>().unwrap()
+        );
+        assert_eq!(
+            vec!["name", "pairs", "last"],
+            document
+                .root()
+                .unwrap()
+                .members()
+                .unwrap()
+                .iter()
+                .map(|(key, _)| key.as_str())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    pub fn test_lazy_positions_are_absolute() {
+        let input = "{\"a\": 1,\n \"b\": {\"c\": true}}";
+        let document = LazyDocument::new(input.as_bytes());
+        let b = document.root().unwrap().get("b").unwrap().unwrap();
+
+        assert_eq!(
+            Position {
+                offset: 15,
+                line: 2,
+                column: 7
+            },
+            b.start()
+        );
+        assert_eq!(
+            "invalid type: boolean `true`, expected f64 at line 2 column 13 (byte 21)",
+            b.get("c")
+                .unwrap()
+                .unwrap()
+                .parse::<f64>()
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[rstest]
+    #[case(
+        "{\"a\": [1, 2 3], \"b\": 1}",
+        "expected `,` or `]` but found number at line 1 column 13 (byte 12)"
+    )]
+    #[case(
+        "{\"a\": [1, {\"x\" 1}], \"b\": 1}",
+        "expected `:` but found number at line 1 column 16 (byte 15)"
+    )]
+    #[case(
+        "{\"a\": [1, }], \"b\": 1}",
+        "expected value but found `}` at line 1 column 11 (byte 10)"
+    )]
+    #[case(
+        "{\"a\": {\"x\": [}}, \"b\": 1}",
+        "expected value but found `}` at line 1 column 14 (byte 13)"
+    )]
+    pub fn test_lazy_skip_checks_skipped_subtrees(#[case] input: &str, #[case] message: &str) {
+        let document = LazyDocument::new(input.as_bytes());
+
+        assert_eq!(
+            message,
+            document.root().unwrap().get("b").unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    pub fn test_lazy_validate() {
+        assert!(LazyDocument::new(DOCUMENT.as_bytes()).validate().is_ok());
+        assert!(LazyDocument::new(b"[1] 2").validate().is_err());
+    }
+}
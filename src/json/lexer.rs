@@ -0,0 +1,751 @@
+use std::{
+    fmt,
+    io::{self, BufRead},
+    str,
+};
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Token {
+    BeginObject,
+    EndObject,
+    BeginArray,
+    EndArray,
+    Colon,
+    Comma,
+    String(String),
+    Identifier(String),
+    Number(f64),
+    TrueValue,
+    FalseValue,
+    NullValue,
+    EndOfInput,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::BeginObject => write!(f, "`{{`"),
+            Token::EndObject => write!(f, "`}}`"),
+            Token::BeginArray => write!(f, "`[`"),
+            Token::EndArray => write!(f, "`]`"),
+            Token::Colon => write!(f, "`:`"),
+            Token::Comma => write!(f, "`,`"),
+            Token::String(_) => write!(f, "string"),
+            Token::Identifier(_) => write!(f, "identifier"),
+            Token::Number(_) => write!(f, "number"),
+            Token::TrueValue => write!(f, "`true`"),
+            Token::FalseValue => write!(f, "`false`"),
+            Token::NullValue => write!(f, "`null`"),
+            Token::EndOfInput => write!(f, "end of input"),
+        }
+    }
+}
+
+// Lines and columns start at 1. Columns count characters, not bytes, so a
+// multi-byte UTF-8 sequence only advances the column once.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Position {
+    pub offset: u64,
+    pub line: u64,
+    pub column: u64,
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Position {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {} column {} (byte {})",
+            self.line, self.column, self.offset
+        )
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum LexerErrorKind {
+    UnexpectedByte(u8),
+    UnexpectedEnd,
+    InvalidEscape,
+    InvalidNumber,
+    InvalidUtf8,
+    ControlCharInString,
+    Io(io::ErrorKind),
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct LexerError {
+    pub position: Position,
+    pub kind: LexerErrorKind,
+}
+
+impl fmt::Display for LexerErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LexerErrorKind::UnexpectedByte(b) if b.is_ascii_graphic() => {
+                write!(f, "unexpected character `{}`", *b as char)
+            }
+            LexerErrorKind::UnexpectedByte(b) => write!(f, "unexpected byte 0x{:02x}", b),
+            LexerErrorKind::UnexpectedEnd => write!(f, "unexpected end of input"),
+            LexerErrorKind::InvalidEscape => write!(f, "invalid escape sequence"),
+            LexerErrorKind::InvalidNumber => write!(f, "invalid number"),
+            LexerErrorKind::InvalidUtf8 => write!(f, "invalid UTF-8 in string"),
+            LexerErrorKind::ControlCharInString => write!(f, "control character in string"),
+            LexerErrorKind::Io(kind) => write!(f, "read failed: {:?}", kind),
+        }
+    }
+}
+
+impl fmt::Display for LexerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.kind, self.position)
+    }
+}
+
+// Extensions beyond strict JSON for hand-written files, in the spirit of JSONC
+// and JSON5. Every one is off by default and can be switched on by itself.
+// They apply to everything built on the lexer, not to json::tokenizer.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct ParseOptions {
+    // `// line` and `/* block */` comments wherever whitespace is allowed.
+    pub comments: bool,
+    // A single `,` before the closing `]` or `}`.
+    pub trailing_commas: bool,
+    // Strings delimited by `'`, in which `\'` is a valid escape.
+    pub single_quotes: bool,
+    // Object keys written as bare identifiers such as `pairs`.
+    pub unquoted_keys: bool,
+    // Integers such as `0x1F` and `-0xff`.
+    pub hex_numbers: bool,
+    // `Infinity`, `-Infinity` and `NaN`.
+    pub non_finite_numbers: bool,
+}
+
+impl ParseOptions {
+    pub fn lenient() -> ParseOptions {
+        ParseOptions {
+            comments: true,
+            trailing_commas: true,
+            single_quotes: true,
+            unquoted_keys: true,
+            hex_numbers: true,
+            non_finite_numbers: true,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct LexedToken {
+    pub token: Token,
+    pub start: Position,
+    pub end: Position,
+}
+
+// Pull-based tokenizer that reads one token at a time and keeps track of
+// where in the input every token starts and ends.
+pub struct Lexer<R> {
+    reader: R,
+    position: Position,
+    peeked: Option<LexedToken>,
+    options: ParseOptions,
+    // Reused for the text of every string and number, so that callers who
+    // discard most tokens do not allocate for each of them.
+    scratch: Vec<u8>,
+}
+
+pub(super) fn is_json_whitespace(b: u8) -> bool {
+    b == b' ' || b == b'\t' || b == b'\n' || b == b'\r'
+}
+
+fn is_identifier_start(b: u8) -> bool {
+    b.is_ascii_alphabetic() || b == b'_' || b == b'$'
+}
+
+fn is_identifier_part(b: u8) -> bool {
+    is_identifier_start(b) || b.is_ascii_digit()
+}
+
+impl<R> Lexer<R>
+where
+    R: BufRead,
+{
+    pub fn new(reader: R) -> Lexer<R> {
+        Lexer::with_options(reader, ParseOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: ParseOptions) -> Lexer<R> {
+        Lexer::with_position(reader, options, Position::default())
+    }
+
+    // For readers that start in the middle of a document, at a token whose
+    // position is already known, so reported positions stay absolute.
+    pub fn with_position(reader: R, options: ParseOptions, position: Position) -> Lexer<R> {
+        Lexer {
+            reader,
+            position,
+            peeked: None,
+            options,
+            scratch: Vec::new(),
+        }
+    }
+
+    pub fn options(&self) -> ParseOptions {
+        self.options
+    }
+
+    fn error(&self, kind: LexerErrorKind) -> LexerError {
+        LexerError {
+            position: self.position,
+            kind,
+        }
+    }
+
+    fn peek_byte(&mut self) -> Result<Option<u8>, LexerError> {
+        loop {
+            match self.reader.fill_buf() {
+                Ok(buffer) => return Ok(buffer.first().copied()),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(self.error(LexerErrorKind::Io(e.kind()))),
+            }
+        }
+    }
+
+    fn next_byte(&mut self) -> Result<Option<u8>, LexerError> {
+        let b = self.peek_byte()?;
+        if let Some(b) = b {
+            self.reader.consume(1);
+            self.position.offset += 1;
+            if b == b'\n' {
+                self.position.line += 1;
+                self.position.column = 1;
+            } else if b & 0xC0 != 0x80 {
+                self.position.column += 1;
+            }
+        }
+        Ok(b)
+    }
+
+    fn expect_byte(&mut self) -> Result<u8, LexerError> {
+        self.next_byte()?
+            .ok_or_else(|| self.error(LexerErrorKind::UnexpectedEnd))
+    }
+
+    fn skip_whitespace(&mut self) -> Result<(), LexerError> {
+        loop {
+            while self.peek_byte()?.is_some_and(is_json_whitespace) {
+                self.next_byte()?;
+            }
+            if !self.options.comments || self.peek_byte()? != Some(b'/') {
+                return Ok(());
+            }
+            self.skip_comment()?;
+        }
+    }
+
+    fn skip_comment(&mut self) -> Result<(), LexerError> {
+        self.next_byte()?;
+        let position = self.position;
+        match self.expect_byte()? {
+            b'/' => {
+                while let Some(b) = self.next_byte()? {
+                    if b == b'\n' {
+                        break;
+                    }
+                }
+            }
+            b'*' => {
+                let mut previous = 0;
+                loop {
+                    let b = self.expect_byte()?;
+                    if previous == b'*' && b == b'/' {
+                        break;
+                    }
+                    previous = b;
+                }
+            }
+            b => {
+                return Err(LexerError {
+                    position,
+                    kind: LexerErrorKind::UnexpectedByte(b),
+                })
+            }
+        }
+        Ok(())
+    }
+
+    pub fn peek(&mut self) -> Result<&LexedToken, LexerError> {
+        if self.peeked.is_none() {
+            let token = self.read_token(false)?;
+            self.peeked = Some(token);
+        }
+        Ok(self.peeked.as_ref().unwrap())
+    }
+
+    pub fn next_token(&mut self) -> Result<LexedToken, LexerError> {
+        match self.peeked.take() {
+            Some(token) => Ok(token),
+            None => self.read_token(false),
+        }
+    }
+
+    // Same as next_token, except that string tokens come back empty and their
+    // contents are only available through last_string until the next token
+    // is read. Nothing is allocated for the token itself.
+    pub fn next_token_reusing(&mut self) -> Result<LexedToken, LexerError> {
+        match self.peeked.take() {
+            Some(token) => {
+                if let Token::String(str) = &token.token {
+                    self.scratch.clear();
+                    self.scratch.extend_from_slice(str.as_bytes());
+                }
+                Ok(token)
+            }
+            None => self.read_token(true),
+        }
+    }
+
+    // Drops what is left of a malformed token after an error, up to the next
+    // whitespace or structural character, so lexing can go on from there.
+    pub fn skip_malformed(&mut self) -> Result<(), LexerError> {
+        self.peeked = None;
+        while let Some(b) = self.peek_byte()? {
+            if is_json_whitespace(b) || b"{}[]:,".contains(&b) {
+                break;
+            }
+            self.next_byte()?;
+        }
+        Ok(())
+    }
+
+    pub fn last_string(&self) -> &str {
+        str::from_utf8(&self.scratch).unwrap_or_default()
+    }
+
+    fn read_token(&mut self, reusing: bool) -> Result<LexedToken, LexerError> {
+        self.skip_whitespace()?;
+        let start = self.position;
+        let Some(b) = self.peek_byte()? else {
+            return Ok(LexedToken {
+                token: Token::EndOfInput,
+                start,
+                end: start,
+            });
+        };
+        let token = match b {
+            b'{' | b'}' | b'[' | b']' | b':' | b',' => {
+                self.next_byte()?;
+                match b {
+                    b'{' => Token::BeginObject,
+                    b'}' => Token::EndObject,
+                    b'[' => Token::BeginArray,
+                    b']' => Token::EndArray,
+                    b':' => Token::Colon,
+                    _ => Token::Comma,
+                }
+            }
+            b if is_identifier_start(b)
+                && (self.options.unquoted_keys || self.options.non_finite_numbers) =>
+            {
+                self.read_identifier()?
+            }
+            b't' => self.read_literal(b"true", Token::TrueValue)?,
+            b'f' => self.read_literal(b"false", Token::FalseValue)?,
+            b'n' => self.read_literal(b"null", Token::NullValue)?,
+            b'"' => self.read_string(reusing)?,
+            b'\'' if self.options.single_quotes => self.read_string(reusing)?,
+            b'-' | b'0'..=b'9' => Token::Number(self.read_number()?),
+            b => return Err(self.error(LexerErrorKind::UnexpectedByte(b))),
+        };
+        Ok(LexedToken {
+            token,
+            start,
+            end: self.position,
+        })
+    }
+
+    fn expect_literal(&mut self, literal: &[u8]) -> Result<(), LexerError> {
+        for expected in literal {
+            match self.peek_byte()? {
+                Some(b) if b == *expected => {
+                    self.next_byte()?;
+                }
+                Some(b) => return Err(self.error(LexerErrorKind::UnexpectedByte(b))),
+                None => return Err(self.error(LexerErrorKind::UnexpectedEnd)),
+            }
+        }
+        Ok(())
+    }
+
+    fn read_literal(&mut self, literal: &[u8], token: Token) -> Result<Token, LexerError> {
+        self.expect_literal(literal)?;
+        Ok(token)
+    }
+
+    // Only reached when identifiers can mean something, otherwise the literal
+    // rules above give the more precise error position.
+    fn read_identifier(&mut self) -> Result<Token, LexerError> {
+        let start = self.position;
+        let mut result = String::new();
+        while let Some(b) = self.peek_byte()? {
+            if !is_identifier_part(b) {
+                break;
+            }
+            result.push(b as char);
+            self.next_byte()?;
+        }
+        match result.as_str() {
+            "true" => Ok(Token::TrueValue),
+            "false" => Ok(Token::FalseValue),
+            "null" => Ok(Token::NullValue),
+            "Infinity" if self.options.non_finite_numbers => Ok(Token::Number(f64::INFINITY)),
+            "NaN" if self.options.non_finite_numbers => Ok(Token::Number(f64::NAN)),
+            _ if self.options.unquoted_keys => Ok(Token::Identifier(result)),
+            _ => Err(LexerError {
+                position: start,
+                kind: LexerErrorKind::UnexpectedByte(result.as_bytes()[0]),
+            }),
+        }
+    }
+
+    fn read_digits(&mut self, is_digit: fn(&u8) -> bool) -> Result<usize, LexerError> {
+        let mut count = 0;
+        while let Some(b) = self.peek_byte()? {
+            if !is_digit(&b) {
+                break;
+            }
+            self.scratch.push(b);
+            self.next_byte()?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    // Follows the JSON number grammar exactly: no leading zeros, no leading
+    // "+", and at least one digit after "." and after the exponent marker.
+    fn read_number(&mut self) -> Result<f64, LexerError> {
+        let start = self.position;
+        let invalid = LexerError {
+            position: start,
+            kind: LexerErrorKind::InvalidNumber,
+        };
+        self.scratch.clear();
+        let mut sign = 1.0;
+        if self.peek_byte()? == Some(b'-') {
+            self.scratch.push(b'-');
+            sign = -1.0;
+            self.next_byte()?;
+        }
+        if self.options.non_finite_numbers && self.peek_byte()? == Some(b'I') {
+            self.expect_literal(b"Infinity")?;
+            return Ok(sign * f64::INFINITY);
+        }
+        if self.peek_byte()? == Some(b'0') {
+            self.scratch.push(b'0');
+            self.next_byte()?;
+            if self.options.hex_numbers && matches!(self.peek_byte()?, Some(b'x' | b'X')) {
+                self.next_byte()?;
+                self.scratch.clear();
+                self.read_digits(u8::is_ascii_hexdigit)?;
+                return u64::from_str_radix(self.last_string(), 16)
+                    .map(|number| sign * number as f64)
+                    .map_err(|_| invalid);
+            }
+            if self.peek_byte()?.is_some_and(|b| b.is_ascii_digit()) {
+                return Err(invalid);
+            }
+        } else if self.read_digits(u8::is_ascii_digit)? == 0 {
+            return Err(invalid);
+        }
+        if self.peek_byte()? == Some(b'.') {
+            self.scratch.push(b'.');
+            self.next_byte()?;
+            if self.read_digits(u8::is_ascii_digit)? == 0 {
+                return Err(invalid);
+            }
+        }
+        if let Some(b @ (b'e' | b'E')) = self.peek_byte()? {
+            self.scratch.push(b);
+            self.next_byte()?;
+            if let Some(b @ (b'+' | b'-')) = self.peek_byte()? {
+                self.scratch.push(b);
+                self.next_byte()?;
+            }
+            if self.read_digits(u8::is_ascii_digit)? == 0 {
+                return Err(invalid);
+            }
+        }
+        self.last_string().parse().map_err(|_| invalid)
+    }
+
+    fn read_hex_escape(&mut self) -> Result<u32, LexerError> {
+        let mut result = 0;
+        for _ in 0..4 {
+            let position = self.position;
+            let digit = (self.expect_byte()? as char)
+                .to_digit(16)
+                .ok_or(LexerError {
+                    position,
+                    kind: LexerErrorKind::InvalidEscape,
+                })?;
+            result = result * 16 + digit;
+        }
+        Ok(result)
+    }
+
+    fn read_unicode_escape(&mut self) -> Result<char, LexerError> {
+        let start = self.position;
+        let invalid = LexerError {
+            position: start,
+            kind: LexerErrorKind::InvalidEscape,
+        };
+        let first = self.read_hex_escape()?;
+        let code_point = match first {
+            0xD800..=0xDBFF => {
+                if self.expect_byte()? != b'\\' || self.expect_byte()? != b'u' {
+                    return Err(invalid);
+                }
+                let second = self.read_hex_escape()?;
+                if !(0xDC00..=0xDFFF).contains(&second) {
+                    return Err(invalid);
+                }
+                0x10000 + ((first - 0xD800) << 10) + (second - 0xDC00)
+            }
+            0xDC00..=0xDFFF => return Err(invalid),
+            code_point => code_point,
+        };
+        char::from_u32(code_point).ok_or(invalid)
+    }
+
+    fn read_string(&mut self, reusing: bool) -> Result<Token, LexerError> {
+        let start = self.position;
+        let quote = self.expect_byte()?;
+        self.scratch.clear();
+        loop {
+            let position = self.position;
+            match self.expect_byte()? {
+                b if b == quote => break,
+                b'\\' => {
+                    let c = match self.expect_byte()? {
+                        b'"' => '"',
+                        b'\'' if self.options.single_quotes => '\'',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{0008}',
+                        b'f' => '\u{000C}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.read_unicode_escape()?,
+                        _ => {
+                            return Err(LexerError {
+                                position,
+                                kind: LexerErrorKind::InvalidEscape,
+                            })
+                        }
+                    };
+                    let mut buffer = [0; 4];
+                    self.scratch
+                        .extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+                }
+                b if b < 0x20 => {
+                    return Err(LexerError {
+                        position,
+                        kind: LexerErrorKind::ControlCharInString,
+                    })
+                }
+                b => self.scratch.push(b),
+            }
+        }
+        let Ok(str) = str::from_utf8(&self.scratch) else {
+            return Err(LexerError {
+                position: start,
+                kind: LexerErrorKind::InvalidUtf8,
+            });
+        };
+        Ok(Token::String(if reusing {
+            String::new()
+        } else {
+            str.to_string()
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::json::lexer::*;
+
+    fn tokens(input: &str) -> Result<Vec<Token>, LexerError> {
+        let mut lexer = Lexer::new(input.as_bytes());
+        let mut result = Vec::new();
+        loop {
+            let token = lexer.next_token()?.token;
+            if token == Token::EndOfInput {
+                return Ok(result);
+            }
+            result.push(token);
+        }
+    }
+
+    #[test]
+    pub fn test_lexer_tokens() {
+        assert_eq!(
+            Ok(vec![
+                Token::BeginObject,
+                Token::String("pairs".to_string()),
+                Token::Colon,
+                Token::BeginArray,
+                Token::Number(-1.5e2),
+                Token::Comma,
+                Token::TrueValue,
+                Token::Comma,
+                Token::FalseValue,
+                Token::Comma,
+                Token::NullValue,
+                Token::EndArray,
+                Token::EndObject,
+            ]),
+            tokens("{\"pairs\":\n\t[-1.5e2, true,false ,null]\r\n}")
+        );
+    }
+
+    #[rstest]
+    #[case("\"a\\\"b\\\\c\\/d\"", "a\"b\\c/d")]
+    #[case("\"\\b\\f\\n\\r\\t\"", "\u{0008}\u{000C}\n\r\t")]
+    #[case("\"\\u004D\\u00e9\"", "Mé")]
+    #[case("\"\\ud83d\\ude00\"", "😀")]
+    #[case("\"héllo\"", "héllo")]
+    pub fn test_lexer_strings(#[case] input: &str, #[case] expected: &str) {
+        assert_eq!(Ok(vec![Token::String(expected.to_string())]), tokens(input));
+    }
+
+    #[rstest]
+    #[case("0", 0.0)]
+    #[case("-0.5", -0.5)]
+    #[case("12E+2", 1200.0)]
+    #[case("3e-1", 0.3)]
+    pub fn test_lexer_numbers(#[case] input: &str, #[case] expected: f64) {
+        assert_eq!(Ok(vec![Token::Number(expected)]), tokens(input));
+    }
+
+    #[rstest]
+    #[case("01", LexerErrorKind::InvalidNumber, 0)]
+    #[case("1.", LexerErrorKind::InvalidNumber, 0)]
+    #[case("[-]", LexerErrorKind::InvalidNumber, 1)]
+    #[case("1e", LexerErrorKind::InvalidNumber, 0)]
+    #[case("[tru]", LexerErrorKind::UnexpectedByte(b']'), 4)]
+    #[case("\"ab", LexerErrorKind::UnexpectedEnd, 3)]
+    #[case("\"a\\x\"", LexerErrorKind::InvalidEscape, 2)]
+    #[case("\"\\ud83d\"", LexerErrorKind::InvalidEscape, 3)]
+    #[case("\"a\nb\"", LexerErrorKind::ControlCharInString, 2)]
+    #[case("  @", LexerErrorKind::UnexpectedByte(b'@'), 2)]
+    pub fn test_lexer_errors(
+        #[case] input: &str,
+        #[case] kind: LexerErrorKind,
+        #[case] offset: u64,
+    ) {
+        let error = tokens(input).unwrap_err();
+
+        assert_eq!(kind, error.kind);
+        assert_eq!(offset, error.position.offset);
+    }
+
+    fn lenient_tokens(input: &str) -> Result<Vec<Token>, LexerError> {
+        let mut lexer = Lexer::with_options(input.as_bytes(), ParseOptions::lenient());
+        let mut result = Vec::new();
+        loop {
+            let token = lexer.next_token()?.token;
+            if token == Token::EndOfInput {
+                return Ok(result);
+            }
+            result.push(token);
+        }
+    }
+
+    #[rstest]
+    #[case("1 // one\n/* two\n */ 2", vec![Token::Number(1.0), Token::Number(2.0)])]
+    #[case("'it\\'s \"x\"'", vec![Token::String("it's \"x\"".to_string())])]
+    #[case("pairs: $x_1", vec![
+        Token::Identifier("pairs".to_string()),
+        Token::Colon,
+        Token::Identifier("$x_1".to_string())
+    ])]
+    #[case("true null", vec![Token::TrueValue, Token::NullValue])]
+    #[case("0x1F -0XfF 0", vec![Token::Number(31.0), Token::Number(-255.0), Token::Number(0.0)])]
+    #[case("Infinity -Infinity", vec![Token::Number(f64::INFINITY), Token::Number(f64::NEG_INFINITY)])]
+    pub fn test_lexer_lenient_tokens(#[case] input: &str, #[case] expected: Vec<Token>) {
+        assert_eq!(Ok(expected), lenient_tokens(input));
+    }
+
+    #[rstest]
+    #[case("1 /* open", LexerErrorKind::UnexpectedEnd, 9)]
+    #[case("1 /x", LexerErrorKind::UnexpectedByte(b'x'), 3)]
+    #[case("0x", LexerErrorKind::InvalidNumber, 0)]
+    #[case("-Inf", LexerErrorKind::UnexpectedEnd, 4)]
+    pub fn test_lexer_lenient_errors(
+        #[case] input: &str,
+        #[case] kind: LexerErrorKind,
+        #[case] offset: u64,
+    ) {
+        let error = lenient_tokens(input).unwrap_err();
+
+        assert_eq!(kind, error.kind);
+        assert_eq!(offset, error.position.offset);
+    }
+
+    #[rstest]
+    #[case("// x")]
+    #[case("'x'")]
+    #[case("x")]
+    #[case("0x1")]
+    #[case("NaN")]
+    pub fn test_lexer_strict_by_default(#[case] input: &str) {
+        assert!(tokens(input).is_err());
+    }
+
+    #[test]
+    pub fn test_lexer_positions() {
+        let mut lexer = Lexer::new("{\n  \"é\": 10\n}".as_bytes());
+
+        lexer.next_token().unwrap();
+        let key = lexer.next_token().unwrap();
+        lexer.next_token().unwrap();
+        let value = lexer.next_token().unwrap();
+
+        assert_eq!(
+            Position {
+                offset: 4,
+                line: 2,
+                column: 3
+            },
+            key.start
+        );
+        assert_eq!(
+            Position {
+                offset: 10,
+                line: 2,
+                column: 8
+            },
+            value.start
+        );
+        assert_eq!(12, value.end.offset);
+    }
+
+    #[test]
+    pub fn test_lexer_peek() {
+        let mut lexer = Lexer::new("[1]".as_bytes());
+
+        assert_eq!(Token::BeginArray, lexer.peek().unwrap().token);
+        assert_eq!(Token::BeginArray, lexer.next_token().unwrap().token);
+        assert_eq!(Token::Number(1.0), lexer.peek().unwrap().token);
+    }
+}
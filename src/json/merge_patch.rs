@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+
+use super::tokenizer::JsonValue;
+
+// RFC 7386: members set to null are removed, nested objects are merged and
+// every other value, arrays included, replaces what was in the target.
+#[allow(dead_code)]
+pub fn merge_patch(target: &mut JsonValue, patch: &JsonValue) {
+    let JsonValue::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !matches!(target, JsonValue::Object(_)) {
+        *target = JsonValue::Object(HashMap::new());
+    }
+    let JsonValue::Object(target) = target else {
+        unreachable!();
+    };
+    for (key, value) in patch {
+        if *value == JsonValue::NullValue {
+            target.remove(key);
+            continue;
+        }
+        let member = target.entry(key.clone()).or_insert(JsonValue::NullValue);
+        merge_patch(member, value);
+    }
+}
+
+// Produces the patch that turns `from` into `to` when given to merge_patch.
+// Merge patches cannot express a member whose new value is null, since null
+// means removal, so such members are removed instead.
+#[allow(dead_code)]
+pub fn create_merge_patch(from: &JsonValue, to: &JsonValue) -> JsonValue {
+    let (JsonValue::Object(from), JsonValue::Object(to)) = (from, to) else {
+        return to.clone();
+    };
+    let mut patch = HashMap::new();
+    for key in from.keys() {
+        if !to.contains_key(key) {
+            patch.insert(key.clone(), JsonValue::NullValue);
+        }
+    }
+    for (key, value) in to {
+        match from.get(key) {
+            Some(previous) if previous == value => {}
+            Some(previous) => {
+                patch.insert(key.clone(), create_merge_patch(previous, value));
+            }
+            None => {
+                patch.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    JsonValue::Object(patch)
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::json::merge_patch::*;
+    use crate::json::tokenizer::parse_str;
+
+    #[rstest]
+    #[case("{\"a\": \"b\"}", "{\"a\": \"c\"}", "{\"a\": \"c\"}")]
+    #[case("{\"a\": \"b\"}", "{\"b\": \"c\"}", "{\"a\": \"b\", \"b\": \"c\"}")]
+    #[case("{\"a\": \"b\"}", "{\"a\": null}", "{}")]
+    #[case("{\"a\": \"b\", \"b\": \"c\"}", "{\"a\": null}", "{\"b\": \"c\"}")]
+    #[case("{\"a\": [\"b\"]}", "{\"a\": \"c\"}", "{\"a\": \"c\"}")]
+    #[case("{\"a\": \"c\"}", "{\"a\": [\"b\"]}", "{\"a\": [\"b\"]}")]
+    #[case(
+        "{\"a\": {\"b\": \"c\"}}",
+        "{\"a\": {\"b\": \"d\", \"c\": null}}",
+        "{\"a\": {\"b\": \"d\"}}"
+    )]
+    #[case("{\"a\": [{\"b\": \"c\"}]}", "{\"a\": [1]}", "{\"a\": [1]}")]
+    #[case("[\"a\", \"b\"]", "[\"c\", \"d\"]", "[\"c\", \"d\"]")]
+    #[case("{\"a\": \"b\"}", "[\"c\"]", "[\"c\"]")]
+    #[case("{\"a\": \"foo\"}", "null", "null")]
+    #[case("{\"e\": null}", "{\"a\": 1}", "{\"e\": null, \"a\": 1}")]
+    #[case("[1, 2]", "{\"a\": \"b\", \"c\": null}", "{\"a\": \"b\"}")]
+    #[case("{}", "{\"a\": {\"bb\": {\"ccc\": null}}}", "{\"a\": {\"bb\": {}}}")]
+    pub fn test_merge_patch(#[case] target: &str, #[case] patch: &str, #[case] expected: &str) {
+        let mut target = parse_str(target).unwrap();
+
+        merge_patch(&mut target, &parse_str(patch).unwrap());
+
+        assert_eq!(parse_str(expected).unwrap(), target);
+    }
+
+    #[rstest]
+    #[case("{\"a\": 1, \"b\": 2}", "{\"a\": 1, \"b\": 3}", "{\"b\": 3}")]
+    #[case("{\"a\": 1, \"b\": 2}", "{\"a\": 1}", "{\"b\": null}")]
+    #[case(
+        "{\"a\": {\"x\": 1, \"y\": 2}}",
+        "{\"a\": {\"x\": 1, \"y\": 5}}",
+        "{\"a\": {\"y\": 5}}"
+    )]
+    #[case("{\"a\": [1, 2]}", "{\"a\": [1]}", "{\"a\": [1]}")]
+    #[case("[1]", "{\"a\": 1}", "{\"a\": 1}")]
+    pub fn test_create_merge_patch(#[case] from: &str, #[case] to: &str, #[case] expected: &str) {
+        let from = parse_str(from).unwrap();
+        let to = parse_str(to).unwrap();
+
+        let patch = create_merge_patch(&from, &to);
+        let mut patched = from.clone();
+        merge_patch(&mut patched, &patch);
+
+        assert_eq!(parse_str(expected).unwrap(), patch);
+        assert_eq!(to, patched);
+    }
+}
@@ -1 +1,29 @@
-mod tokenizer;
+mod access;
+pub mod cli;
+mod cache;
+mod cbor;
+mod convert;
+pub mod de;
+mod diagnostic;
+mod diff;
+mod extract;
+mod footprint;
+pub mod from_json;
+mod index;
+mod lazy;
+mod lexer;
+mod merge_patch;
+mod msgpack;
+mod parallel;
+mod patch;
+mod path;
+mod pointer;
+mod recover;
+mod schema;
+pub mod ser;
+mod spans;
+pub mod tokenizer;
+#[cfg(test)]
+mod test_support;
+mod verify;
+mod writer;
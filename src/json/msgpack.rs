@@ -0,0 +1,544 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    io::{self, Write},
+    str,
+};
+
+use super::tokenizer::JsonValue;
+
+const NIL: u8 = 0xc0;
+const NEVER_USED: u8 = 0xc1;
+const FALSE: u8 = 0xc2;
+const TRUE: u8 = 0xc3;
+const BIN8: u8 = 0xc4;
+const BIN16: u8 = 0xc5;
+const BIN32: u8 = 0xc6;
+const EXT8: u8 = 0xc7;
+const EXT16: u8 = 0xc8;
+const EXT32: u8 = 0xc9;
+const FLOAT32: u8 = 0xca;
+const FLOAT64: u8 = 0xcb;
+const UINT8: u8 = 0xcc;
+const UINT16: u8 = 0xcd;
+const UINT32: u8 = 0xce;
+const UINT64: u8 = 0xcf;
+const INT8: u8 = 0xd0;
+const INT16: u8 = 0xd1;
+const INT32: u8 = 0xd2;
+const INT64: u8 = 0xd3;
+const FIXEXT1: u8 = 0xd4;
+const FIXEXT16: u8 = 0xd8;
+const STR8: u8 = 0xd9;
+const STR16: u8 = 0xda;
+const STR32: u8 = 0xdb;
+const ARRAY16: u8 = 0xdc;
+const ARRAY32: u8 = 0xdd;
+const MAP16: u8 = 0xde;
+const MAP32: u8 = 0xdf;
+
+const TIMESTAMP: i8 = -1;
+
+// Deepest nesting of arrays and maps decoded before giving up, so untrusted
+// input cannot overflow the stack.
+const MAX_DEPTH: usize = 128;
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum MsgPackError {
+    UnexpectedEnd,
+    // Extension types carry application data JSON cannot hold. Holds the
+    // extension type and the offset of the value.
+    UnsupportedExt(i8, usize),
+    InvalidByte(u8, usize),
+    InvalidUtf8(usize),
+    NonStringKey(usize),
+    // Nesting beyond MAX_DEPTH. Holds the offset of the value too deep.
+    TooDeep(usize),
+    TrailingBytes(usize),
+}
+
+impl fmt::Display for MsgPackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MsgPackError::UnexpectedEnd => write!(f, "unexpected end of input"),
+            MsgPackError::UnsupportedExt(TIMESTAMP, offset) => {
+                write!(f, "unsupported ext type -1 (timestamp) at byte {}", offset)
+            }
+            MsgPackError::UnsupportedExt(ext_type, offset) => {
+                write!(f, "unsupported ext type {} at byte {}", ext_type, offset)
+            }
+            MsgPackError::InvalidByte(b, offset) => {
+                write!(f, "invalid byte 0x{:02x} at byte {}", b, offset)
+            }
+            MsgPackError::InvalidUtf8(offset) => write!(f, "invalid UTF-8 at byte {}", offset),
+            MsgPackError::NonStringKey(offset) => {
+                write!(f, "map key is not a string at byte {}", offset)
+            }
+            MsgPackError::TooDeep(offset) => {
+                write!(f, "nesting too deep at byte {}", offset)
+            }
+            MsgPackError::TrailingBytes(offset) => {
+                write!(f, "unexpected data after the value at byte {}", offset)
+            }
+        }
+    }
+}
+
+// Writes the first of the marker families that can hold `length`: the fix
+// form when given one, then the 8, 16 and 32 bit forms.
+fn write_length<W>(
+    writer: &mut W,
+    length: usize,
+    fix: Option<(u8, usize)>,
+    sized: [Option<u8>; 3],
+) -> io::Result<()>
+where
+    W: Write,
+{
+    match (fix, sized) {
+        (Some((marker, limit)), _) if length < limit => writer.write_all(&[marker | length as u8]),
+        (_, [Some(marker), _, _]) if length <= u8::MAX as usize => {
+            writer.write_all(&[marker, length as u8])
+        }
+        (_, [_, Some(marker), _]) if length <= u16::MAX as usize => {
+            writer.write_all(&[marker])?;
+            writer.write_all(&(length as u16).to_be_bytes())
+        }
+        (_, [_, _, Some(marker)]) => {
+            writer.write_all(&[marker])?;
+            writer.write_all(&(length as u32).to_be_bytes())
+        }
+        _ => unreachable!(),
+    }
+}
+
+// Whole numbers are written in the smallest integer format that holds them,
+// anything else as a float32 when that is exact and a float64 otherwise.
+fn write_number<W>(writer: &mut W, number: f64) -> io::Result<()>
+where
+    W: Write,
+{
+    let is_integer = number.fract() == 0.0 && !(number == 0.0 && number.is_sign_negative());
+    if is_integer && (0.0..18446744073709551616.0).contains(&number) {
+        let n = number as u64;
+        return match n {
+            0..=0x7f => writer.write_all(&[n as u8]),
+            0x80..=0xff => writer.write_all(&[UINT8, n as u8]),
+            0x100..=0xffff => {
+                writer.write_all(&[UINT16])?;
+                writer.write_all(&(n as u16).to_be_bytes())
+            }
+            0x10000..=0xffff_ffff => {
+                writer.write_all(&[UINT32])?;
+                writer.write_all(&(n as u32).to_be_bytes())
+            }
+            _ => {
+                writer.write_all(&[UINT64])?;
+                writer.write_all(&n.to_be_bytes())
+            }
+        };
+    }
+    if is_integer && (-9223372036854775808.0..0.0).contains(&number) {
+        let n = number as i64;
+        return match n {
+            -32..=-1 => writer.write_all(&[n as u8]),
+            -0x80..=-33 => writer.write_all(&[INT8, n as u8]),
+            -0x8000..=-0x81 => {
+                writer.write_all(&[INT16])?;
+                writer.write_all(&(n as i16).to_be_bytes())
+            }
+            -0x8000_0000..=-0x8001 => {
+                writer.write_all(&[INT32])?;
+                writer.write_all(&(n as i32).to_be_bytes())
+            }
+            _ => {
+                writer.write_all(&[INT64])?;
+                writer.write_all(&n.to_be_bytes())
+            }
+        };
+    }
+    if number.is_nan() || number as f32 as f64 == number {
+        writer.write_all(&[FLOAT32])?;
+        return writer.write_all(&(number as f32).to_be_bytes());
+    }
+    writer.write_all(&[FLOAT64])?;
+    writer.write_all(&number.to_be_bytes())
+}
+
+fn write_str<W>(writer: &mut W, str: &str) -> io::Result<()>
+where
+    W: Write,
+{
+    write_length(
+        writer,
+        str.len(),
+        Some((0xa0, 32)),
+        [Some(STR8), Some(STR16), Some(STR32)],
+    )?;
+    writer.write_all(str.as_bytes())
+}
+
+// Map keys are written in sorted order, so the same value always encodes to
+// the same bytes.
+pub fn write_msgpack<W>(writer: &mut W, value: &JsonValue) -> io::Result<()>
+where
+    W: Write,
+{
+    match value {
+        JsonValue::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            write_length(
+                writer,
+                keys.len(),
+                Some((0x80, 16)),
+                [None, Some(MAP16), Some(MAP32)],
+            )?;
+            for key in keys {
+                write_str(writer, key)?;
+                write_msgpack(writer, &map[key])?;
+            }
+            Ok(())
+        }
+        JsonValue::Array(items) => {
+            write_length(
+                writer,
+                items.len(),
+                Some((0x90, 16)),
+                [None, Some(ARRAY16), Some(ARRAY32)],
+            )?;
+            for item in items {
+                write_msgpack(writer, item)?;
+            }
+            Ok(())
+        }
+        JsonValue::String(str) => write_str(writer, str),
+        JsonValue::Number(number) => write_number(writer, *number),
+        JsonValue::TrueValue => writer.write_all(&[TRUE]),
+        JsonValue::FalseValue => writer.write_all(&[FALSE]),
+        JsonValue::NullValue => writer.write_all(&[NIL]),
+    }
+}
+
+#[allow(dead_code)]
+pub fn to_msgpack(value: &JsonValue) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    write_msgpack(&mut bytes, value).unwrap();
+    bytes
+}
+
+struct Decoder<'a> {
+    input: &'a [u8],
+    offset: usize,
+    depth: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], MsgPackError> {
+        let end = self
+            .offset
+            .checked_add(count)
+            .filter(|end| *end <= self.input.len())
+            .ok_or(MsgPackError::UnexpectedEnd)?;
+        let bytes = &self.input[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], MsgPackError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    // Reads a big-endian length of 1, 2 or 4 bytes.
+    fn length(&mut self, size: usize) -> Result<usize, MsgPackError> {
+        let bytes = self.take(size)?;
+        Ok(bytes.iter().fold(0, |value, b| value << 8 | *b as usize))
+    }
+
+    // Strings and binaries both become strings, as some writers still put
+    // text in bin. Binaries that are not UTF-8 have no JSON form.
+    fn text(&mut self, length: usize, start: usize) -> Result<String, MsgPackError> {
+        str::from_utf8(self.take(length)?)
+            .map(str::to_string)
+            .map_err(|_| MsgPackError::InvalidUtf8(start))
+    }
+
+    fn array(&mut self, length: usize) -> Result<JsonValue, MsgPackError> {
+        // Every item takes at least a byte, which bounds what to reserve.
+        let mut items = Vec::with_capacity(length.min(self.input.len() - self.offset));
+        for _ in 0..length {
+            items.push(self.value()?);
+        }
+        Ok(JsonValue::Array(items))
+    }
+
+    fn map(&mut self, length: usize) -> Result<JsonValue, MsgPackError> {
+        let mut members = HashMap::new();
+        for _ in 0..length {
+            let key_start = self.offset;
+            let JsonValue::String(key) = self.value()? else {
+                return Err(MsgPackError::NonStringKey(key_start));
+            };
+            members.insert(key, self.value()?);
+        }
+        Ok(JsonValue::Object(members))
+    }
+
+    fn ext(&mut self, length: usize, start: usize) -> Result<JsonValue, MsgPackError> {
+        let ext_type = self.take(1)?[0] as i8;
+        self.take(length)?;
+        Err(MsgPackError::UnsupportedExt(ext_type, start))
+    }
+
+    fn value(&mut self) -> Result<JsonValue, MsgPackError> {
+        if self.depth == MAX_DEPTH {
+            return Err(MsgPackError::TooDeep(self.offset));
+        }
+        self.depth += 1;
+        let value = self.item();
+        self.depth -= 1;
+        value
+    }
+
+    fn item(&mut self) -> Result<JsonValue, MsgPackError> {
+        let start = self.offset;
+        let marker = self.take(1)?[0];
+        let number = |number| Ok(JsonValue::Number(number));
+        match marker {
+            0x00..=0x7f => number(marker as f64),
+            0x80..=0x8f => self.map((marker & 0x0f) as usize),
+            0x90..=0x9f => self.array((marker & 0x0f) as usize),
+            0xa0..=0xbf => self
+                .text((marker & 0x1f) as usize, start)
+                .map(JsonValue::String),
+            0xe0..=0xff => number(marker as i8 as f64),
+            NIL => Ok(JsonValue::NullValue),
+            FALSE => Ok(JsonValue::FalseValue),
+            TRUE => Ok(JsonValue::TrueValue),
+            BIN8 | STR8 => {
+                let length = self.length(1)?;
+                self.text(length, start).map(JsonValue::String)
+            }
+            BIN16 | STR16 => {
+                let length = self.length(2)?;
+                self.text(length, start).map(JsonValue::String)
+            }
+            BIN32 | STR32 => {
+                let length = self.length(4)?;
+                self.text(length, start).map(JsonValue::String)
+            }
+            FLOAT32 => number(f32::from_be_bytes(self.take_array()?) as f64),
+            FLOAT64 => number(f64::from_be_bytes(self.take_array()?)),
+            UINT8 => number(self.take_array::<1>()?[0] as f64),
+            UINT16 => number(u16::from_be_bytes(self.take_array()?) as f64),
+            UINT32 => number(u32::from_be_bytes(self.take_array()?) as f64),
+            UINT64 => number(u64::from_be_bytes(self.take_array()?) as f64),
+            INT8 => number(self.take_array::<1>()?[0] as i8 as f64),
+            INT16 => number(i16::from_be_bytes(self.take_array()?) as f64),
+            INT32 => number(i32::from_be_bytes(self.take_array()?) as f64),
+            INT64 => number(i64::from_be_bytes(self.take_array()?) as f64),
+            ARRAY16 => {
+                let length = self.length(2)?;
+                self.array(length)
+            }
+            ARRAY32 => {
+                let length = self.length(4)?;
+                self.array(length)
+            }
+            MAP16 => {
+                let length = self.length(2)?;
+                self.map(length)
+            }
+            MAP32 => {
+                let length = self.length(4)?;
+                self.map(length)
+            }
+            FIXEXT1..=FIXEXT16 => self.ext(1 << (marker - FIXEXT1), start),
+            EXT8 => {
+                let length = self.length(1)?;
+                self.ext(length, start)
+            }
+            EXT16 => {
+                let length = self.length(2)?;
+                self.ext(length, start)
+            }
+            EXT32 => {
+                let length = self.length(4)?;
+                self.ext(length, start)
+            }
+            NEVER_USED => Err(MsgPackError::InvalidByte(marker, start)),
+        }
+    }
+}
+
+// Decodes exactly one value. Integers beyond 2^53 are rounded to the nearest
+// f64, as they would be from JSON.
+#[allow(dead_code)]
+pub fn from_msgpack(input: &[u8]) -> Result<JsonValue, MsgPackError> {
+    let mut decoder = Decoder {
+        input,
+        offset: 0,
+        depth: 0,
+    };
+    let value = decoder.value()?;
+    if decoder.offset != input.len() {
+        return Err(MsgPackError::TrailingBytes(decoder.offset));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::json::msgpack::*;
+    use crate::json::test_support::{assert_round_trips, hex, unhex};
+    use crate::json::tokenizer::parse_str;
+
+    #[rstest]
+    #[case(0.0, "00")]
+    #[case(127.0, "7f")]
+    #[case(128.0, "cc80")]
+    #[case(255.0, "ccff")]
+    #[case(256.0, "cd0100")]
+    #[case(65536.0, "ce00010000")]
+    #[case(4294967296.0, "cf0000000100000000")]
+    #[case(-1.0, "ff")]
+    #[case(-32.0, "e0")]
+    #[case(-33.0, "d0df")]
+    #[case(-128.0, "d080")]
+    #[case(-129.0, "d1ff7f")]
+    #[case(-32769.0, "d2ffff7fff")]
+    #[case(-2147483649.0, "d3ffffffff7fffffff")]
+    #[case(-0.0, "ca80000000")]
+    #[case(1.5, "ca3fc00000")]
+    #[case(1.1, "cb3ff199999999999a")]
+    #[case(f64::INFINITY, "ca7f800000")]
+    pub fn test_encode_number(#[case] number: f64, #[case] expected: &str) {
+        let bytes = to_msgpack(&JsonValue::Number(number));
+
+        assert_eq!(expected, hex(&bytes));
+        match from_msgpack(&bytes) {
+            Ok(JsonValue::Number(decoded)) => assert_eq!(number.to_bits(), decoded.to_bits()),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[rstest]
+    #[case(31, "bf")]
+    #[case(32, "d920")]
+    #[case(255, "d9ff")]
+    #[case(256, "da0100")]
+    #[case(65536, "db00010000")]
+    pub fn test_encode_str_lengths(#[case] length: usize, #[case] marker: &str) {
+        let value = JsonValue::String("x".repeat(length));
+        let bytes = to_msgpack(&value);
+
+        assert!(hex(&bytes).starts_with(marker));
+        assert_eq!(Ok(value), from_msgpack(&bytes));
+    }
+
+    #[rstest]
+    #[case(15, "9f", "8f")]
+    #[case(16, "dc0010", "de0010")]
+    #[case(65536, "dd00010000", "df00010000")]
+    pub fn test_encode_container_lengths(
+        #[case] length: usize,
+        #[case] array_marker: &str,
+        #[case] map_marker: &str,
+    ) {
+        let array = JsonValue::Array(vec![JsonValue::NullValue; length]);
+        let map = JsonValue::Object(
+            (0..length)
+                .map(|i| (i.to_string(), JsonValue::TrueValue))
+                .collect(),
+        );
+
+        for (value, marker) in [(array, array_marker), (map, map_marker)] {
+            let bytes = to_msgpack(&value);
+            assert!(hex(&bytes).starts_with(marker));
+            assert_eq!(Ok(value), from_msgpack(&bytes));
+        }
+    }
+
+    #[rstest]
+    #[case("82a16201a16190", "{\"a\": [], \"b\": 1}")]
+    #[case("93c0c2c3", "[null, false, true]")]
+    #[case("c40568656c6c6f", "\"hello\"")]
+    #[case("c5000161", "\"a\"")]
+    #[case("c60000000161", "\"a\"")]
+    #[case("da000161", "\"a\"")]
+    #[case("db0000000161", "\"a\"")]
+    #[case("dc0001cd0100", "[256]")]
+    #[case("dd00000001d1ff7f", "[-129]")]
+    #[case("de0001a178d2ffff7fff", "{\"x\": -32769}")]
+    #[case("df00000001c401796f", "{\"y\": 111}")]
+    #[case("cc05", "5")]
+    #[case("cd0005", "5")]
+    #[case("ce00000005", "5")]
+    #[case("cf0000000000000005", "5")]
+    #[case("d0fb", "-5")]
+    #[case("d1fffb", "-5")]
+    #[case("d2fffffffb", "-5")]
+    #[case("d3fffffffffffffffb", "-5")]
+    #[case("cb3ff8000000000000", "1.5")]
+    pub fn test_decode(#[case] msgpack: &str, #[case] json: &str) {
+        assert_eq!(Ok(parse_str(json).unwrap()), from_msgpack(&unhex(msgpack)));
+    }
+
+    #[rstest]
+    #[case("", MsgPackError::UnexpectedEnd)]
+    #[case("92c0", MsgPackError::UnexpectedEnd)]
+    #[case("dd7fffffff", MsgPackError::UnexpectedEnd)]
+    #[case("c0c0", MsgPackError::TrailingBytes(1))]
+    #[case("91c1", MsgPackError::InvalidByte(0xc1, 1))]
+    #[case("a2c328", MsgPackError::InvalidUtf8(0))]
+    #[case("c402fffe", MsgPackError::InvalidUtf8(0))]
+    #[case("810102", MsgPackError::NonStringKey(1))]
+    #[case("d40105", MsgPackError::UnsupportedExt(1, 0))]
+    #[case(
+        "91d8020000000000000000000000000000000000",
+        MsgPackError::UnsupportedExt(2, 1)
+    )]
+    #[case("d6ff00000000", MsgPackError::UnsupportedExt(-1, 0))]
+    #[case("c703050a0b0c", MsgPackError::UnsupportedExt(5, 0))]
+    #[case("c8000105ff", MsgPackError::UnsupportedExt(5, 0))]
+    #[case("c9000000010500", MsgPackError::UnsupportedExt(5, 0))]
+    #[case("c70305", MsgPackError::UnexpectedEnd)]
+    pub fn test_decode_errors(#[case] msgpack: &str, #[case] expected: MsgPackError) {
+        assert_eq!(Err(expected), from_msgpack(&unhex(msgpack)));
+    }
+
+    #[rstest]
+    #[case("91", MAX_DEPTH)]
+    #[case("dc0001", 3 * MAX_DEPTH)]
+    // Keys are values one level down, so the key of the deepest map fails.
+    #[case("81a16b", 3 * MAX_DEPTH - 2)]
+    pub fn test_decode_nesting_limit(#[case] open: &str, #[case] too_deep: usize) {
+        let open = unhex(open);
+        let mut nested = open.repeat(MAX_DEPTH - 1);
+        nested.push(0xc0);
+        assert!(from_msgpack(&nested).is_ok());
+
+        // Deep enough to overflow the stack without the limit.
+        let deep = open.repeat(300_000);
+        assert_eq!(Err(MsgPackError::TooDeep(too_deep)), from_msgpack(&deep));
+    }
+
+    #[test]
+    pub fn test_error_messages() {
+        assert_eq!(
+            "unsupported ext type -1 (timestamp) at byte 0",
+            MsgPackError::UnsupportedExt(-1, 0).to_string()
+        );
+        assert_eq!(
+            "unsupported ext type 7 at byte 3",
+            MsgPackError::UnsupportedExt(7, 3).to_string()
+        );
+    }
+
+    #[test]
+    pub fn test_round_trip_tokenizer_output() {
+        assert_round_trips(to_msgpack, from_msgpack);
+    }
+}
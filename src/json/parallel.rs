@@ -0,0 +1,305 @@
+use std::{collections::HashMap, thread};
+
+use super::{
+    de::{self, unexpected, Error},
+    lazy::{expect_key, skip_value},
+    lexer::{is_json_whitespace, Lexer, ParseOptions, Position, Token},
+    tokenizer::JsonValue,
+};
+
+fn lexer_at(input: &[u8], position: Position) -> Lexer<&[u8]> {
+    Lexer::with_position(
+        &input[position.offset as usize..],
+        ParseOptions::default(),
+        position,
+    )
+}
+
+// Where the elements of an array can be cut apart, found without tokenizing.
+struct Split {
+    // Start of each chunk, just after the `[` or after a separating `,`.
+    starts: Vec<Position>,
+    // Offsets of the `,` ending each chunk but the last, then of the `]`.
+    ends: Vec<usize>,
+    close: Position,
+}
+
+// Scans forward from just after `[` to the matching `]`, tracking only
+// whether it is inside a string and how deeply it is nested. Whenever a
+// chunk has reached its share of the bytes, the next comma at the array's own
+// depth ends it. Returns None when the brackets never balance, the real
+// parser then reports the error.
+fn pre_scan(input: &[u8], open: Position, chunks: usize) -> Option<Split> {
+    let begin = open.offset as usize;
+    let target = (input.len() - begin) / chunks + 1;
+    let mut split = Split {
+        starts: vec![open],
+        ends: Vec::new(),
+        close: open,
+    };
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut line = open.line;
+    // Column of the character `b` belongs to, counted as the bytes go by.
+    let mut column = open.column - 1;
+    for (offset, b) in input.iter().enumerate().skip(begin) {
+        if b & 0xC0 != 0x80 {
+            column += 1;
+        }
+        if in_string {
+            match b {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match b {
+            b'"' => in_string = true,
+            b'\n' => {
+                line += 1;
+                column = 0;
+            }
+            b'[' | b'{' => depth += 1,
+            b']' if depth == 0 => {
+                split.ends.push(offset);
+                split.close = Position {
+                    offset: offset as u64,
+                    line,
+                    column,
+                };
+                return Some(split);
+            }
+            b'}' if depth == 0 => return None,
+            b']' | b'}' => depth -= 1,
+            b',' if depth == 0 && offset - split.starts.last()?.offset as usize >= target => {
+                split.ends.push(offset);
+                split.starts.push(Position {
+                    offset: offset as u64 + 1,
+                    line,
+                    column: column + 1,
+                });
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+// Parses the elements of the array whose `[` ends at `open` on up to
+// `threads` threads, and returns them with the position just after the `]`.
+fn parse_elements(
+    input: &[u8],
+    open: Position,
+    threads: usize,
+) -> Result<(Vec<JsonValue>, Position), Error> {
+    let Some(split) = pre_scan(input, open, threads) else {
+        return Err(Error {
+            message: "unbalanced array".to_string(),
+            position: Some(open),
+        });
+    };
+    let after_close = Position {
+        offset: split.close.offset + 1,
+        line: split.close.line,
+        column: split.close.column + 1,
+    };
+    let inside = &input[open.offset as usize..split.close.offset as usize];
+    if split.starts.len() == 1 && inside.iter().all(|b| is_json_whitespace(*b)) {
+        return Ok((Vec::new(), after_close));
+    }
+    let chunks: Vec<Result<Vec<JsonValue>, Error>> = thread::scope(|scope| {
+        let workers: Vec<_> = split
+            .starts
+            .iter()
+            .zip(&split.ends)
+            .map(|(start, end)| {
+                let chunk = &input[start.offset as usize..*end];
+                scope.spawn(move || {
+                    de::separated_from_lexer(Lexer::with_position(
+                        chunk,
+                        ParseOptions::default(),
+                        *start,
+                    ))
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().unwrap())
+            .collect()
+    });
+    let mut elements = Vec::new();
+    for chunk in chunks {
+        elements.extend(chunk?);
+    }
+    Ok((elements, after_close))
+}
+
+fn expect_end(mut lexer: Lexer<&[u8]>) -> Result<(), Error> {
+    let token = lexer.next_token()?;
+    if token.token != Token::EndOfInput {
+        return Err(unexpected(&token, "end of input"));
+    }
+    Ok(())
+}
+
+fn try_parse_parallel(
+    input: &[u8],
+    member: Option<&str>,
+    threads: usize,
+) -> Result<JsonValue, Error> {
+    let mut lexer = Lexer::new(input);
+    let Some(member) = member else {
+        let token = lexer.next_token()?;
+        if token.token != Token::BeginArray {
+            return Err(unexpected(&token, "`[`"));
+        }
+        let (elements, end) = parse_elements(input, token.end, threads)?;
+        expect_end(lexer_at(input, end))?;
+        return Ok(JsonValue::Array(elements));
+    };
+    let token = lexer.next_token()?;
+    if token.token != Token::BeginObject {
+        return Err(unexpected(&token, "`{`"));
+    }
+    let mut members = HashMap::new();
+    loop {
+        let key = expect_key(&mut lexer)?.to_string();
+        let next = lexer.peek()?;
+        let start = next.start;
+        if key == member && next.token == Token::BeginArray {
+            let open = lexer.next_token()?.end;
+            let (elements, end) = parse_elements(input, open, threads)?;
+            members.insert(key, JsonValue::Array(elements));
+            lexer = lexer_at(input, end);
+        } else {
+            let end = skip_value(&mut lexer)?;
+            let raw = &input[start.offset as usize..end.offset as usize];
+            let value = de::from_lexer(Lexer::with_position(raw, ParseOptions::default(), start))?;
+            members.insert(key, value);
+        }
+        let separator = lexer.next_token()?;
+        match separator.token {
+            Token::Comma => continue,
+            Token::EndObject => break,
+            _ => return Err(unexpected(&separator, "`,` or `}`")),
+        }
+    }
+    expect_end(lexer)?;
+    Ok(JsonValue::Object(members))
+}
+
+// Parses a document whose bulk is one large array, either the root or the
+// given member of the root object, with the elements split across threads.
+// The result is the same as a single-threaded parse: whenever the parallel
+// path fails for any reason, the document is parsed again on one thread so
+// that errors are reported exactly as they would be otherwise.
+#[allow(dead_code)]
+pub fn parse_parallel(
+    input: &[u8],
+    member: Option<&str>,
+    threads: usize,
+) -> Result<JsonValue, Error> {
+    try_parse_parallel(input, member, threads.max(1)).or_else(|_| de::from_reader(input))
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::json::parallel::*;
+
+    #[rstest]
+    #[case("[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]", None)]
+    #[case(
+        "[\"a,]\", \"b\\\"],[\", {\"c\": [1, {\"d\": \"}\"}]}, [[], {}], null, true]",
+        None
+    )]
+    #[case(" [ ] ", None)]
+    #[case("[\n  0.5,\n  -1e3\n]", None)]
+    #[case(
+        "{\"name\": \"run\", \"pairs\": [{\"x0\": 1}, {\"x0\": 2}, {\"x0\": 3}], \"z\": [4, 5]}",
+        Some("pairs")
+    )]
+    #[case("{\"pairs\": 5}", Some("pairs"))]
+    #[case("{\"name\": \"run\"}", Some("pairs"))]
+    pub fn test_parse_parallel_matches_sequential(
+        #[case] input: &str,
+        #[case] member: Option<&str>,
+    ) {
+        let expected: JsonValue = de::from_str(input).unwrap();
+
+        for threads in 1..6 {
+            assert_eq!(
+                Ok(&expected),
+                parse_parallel(input.as_bytes(), member, threads).as_ref()
+            );
+        }
+    }
+
+    #[rstest]
+    #[case("[1, 2, 3, 4, 5, 6,]")]
+    #[case("[1, 2, 3, 4 5, 6]")]
+    #[case("[1, 2, [3, 4, 5, 6]")]
+    #[case("[1, 2, \"3, 4, 5, 6]")]
+    #[case("[1, 2, 3, 4, 5, 6] 7")]
+    #[case("{\"pairs\": [1, 2, 3, 4, 5, 6], }")]
+    #[case("{\"pairs\": [1, 2, 3, 4, 5, 6}")]
+    pub fn test_parse_parallel_errors_match_sequential(#[case] input: &str) {
+        let expected = de::from_str::<JsonValue>(input).unwrap_err();
+
+        for threads in 1..6 {
+            assert_eq!(
+                Err(&expected),
+                parse_parallel(input.as_bytes(), Some("pairs"), threads).as_ref()
+            );
+            assert_eq!(
+                Err(&expected),
+                parse_parallel(input.as_bytes(), None, threads).as_ref()
+            );
+        }
+    }
+
+    #[test]
+    pub fn test_parse_parallel_large_array() {
+        let pairs: Vec<String> = (0..1000)
+            .map(|i| format!("{{\"x0\": {}.25, \"tag\": \"a,\\\"]{}\"}}", i, i))
+            .collect();
+        let input = format!("{{\"pairs\": [\n{}\n]}}", pairs.join(",\n"));
+        let expected: JsonValue = de::from_str(&input).unwrap();
+
+        assert_eq!(
+            Ok(expected),
+            parse_parallel(input.as_bytes(), Some("pairs"), 7)
+        );
+    }
+
+    #[test]
+    pub fn test_pre_scan_positions() {
+        let input = "[1,\n \"é\",\n 3, 4]";
+        let open = Lexer::new(input.as_bytes()).next_token().unwrap().end;
+
+        let split = pre_scan(input.as_bytes(), open, 8).unwrap();
+
+        assert_eq!(
+            Position {
+                offset: 10,
+                line: 2,
+                column: 6
+            },
+            split.starts[1]
+        );
+        assert_eq!(vec![9, 13, 16], split.ends);
+        assert_eq!(
+            Position {
+                offset: 16,
+                line: 3,
+                column: 6
+            },
+            split.close
+        );
+    }
+}
@@ -0,0 +1,337 @@
+use std::collections::HashMap;
+
+use super::{
+    access::json,
+    pointer::{parse_pointer, PointerError},
+    tokenizer::JsonValue,
+};
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum PatchOperation {
+    Add { path: String, value: JsonValue },
+    Remove { path: String },
+    Replace { path: String, value: JsonValue },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: JsonValue },
+}
+
+#[derive(PartialEq, Debug)]
+pub enum PatchErrorKind {
+    InvalidOperation,
+    MissingMember(&'static str),
+    Pointer(PointerError),
+    MoveIntoChild,
+    TestFailed,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct PatchError {
+    pub index: usize,
+    pub path: String,
+    pub kind: PatchErrorKind,
+}
+
+fn string_member(
+    operation: &HashMap<String, JsonValue>,
+    name: &'static str,
+) -> Result<String, PatchErrorKind> {
+    let member = operation
+        .get(name)
+        .ok_or(PatchErrorKind::MissingMember(name))?;
+    member
+        .as_str()
+        .map(String::from)
+        .ok_or(PatchErrorKind::InvalidOperation)
+}
+
+fn value_member(
+    operation: &HashMap<String, JsonValue>,
+    name: &'static str,
+) -> Result<JsonValue, PatchErrorKind> {
+    operation
+        .get(name)
+        .cloned()
+        .ok_or(PatchErrorKind::MissingMember(name))
+}
+
+fn parse_operation(operation: &JsonValue) -> Result<PatchOperation, PatchErrorKind> {
+    let JsonValue::Object(operation) = operation else {
+        return Err(PatchErrorKind::InvalidOperation);
+    };
+    let path = string_member(operation, "path")?;
+    match string_member(operation, "op")?.as_str() {
+        "add" => Ok(PatchOperation::Add {
+            path,
+            value: value_member(operation, "value")?,
+        }),
+        "remove" => Ok(PatchOperation::Remove { path }),
+        "replace" => Ok(PatchOperation::Replace {
+            path,
+            value: value_member(operation, "value")?,
+        }),
+        "move" => Ok(PatchOperation::Move {
+            from: string_member(operation, "from")?,
+            path,
+        }),
+        "copy" => Ok(PatchOperation::Copy {
+            from: string_member(operation, "from")?,
+            path,
+        }),
+        "test" => Ok(PatchOperation::Test {
+            path,
+            value: value_member(operation, "value")?,
+        }),
+        _ => Err(PatchErrorKind::InvalidOperation),
+    }
+}
+
+#[allow(dead_code)]
+pub fn parse_patch(patch: &JsonValue) -> Result<Vec<PatchOperation>, PatchError> {
+    let JsonValue::Array(operations) = patch else {
+        return Err(PatchError {
+            index: 0,
+            path: String::new(),
+            kind: PatchErrorKind::InvalidOperation,
+        });
+    };
+    operations
+        .iter()
+        .enumerate()
+        .map(|(index, operation)| {
+            parse_operation(operation).map_err(|kind| {
+                let path = operation["path"].as_str().unwrap_or_default().to_string();
+                PatchError { index, path, kind }
+            })
+        })
+        .collect()
+}
+
+impl PatchOperation {
+    pub fn path(&self) -> &str {
+        match self {
+            PatchOperation::Add { path, .. }
+            | PatchOperation::Remove { path }
+            | PatchOperation::Replace { path, .. }
+            | PatchOperation::Move { path, .. }
+            | PatchOperation::Copy { path, .. }
+            | PatchOperation::Test { path, .. } => path,
+        }
+    }
+
+    pub fn to_json_value(&self) -> JsonValue {
+        match self {
+            PatchOperation::Add { path, value } => {
+                json!({"op": "add", "path": path.as_str(), "value": value.clone()})
+            }
+            PatchOperation::Remove { path } => json!({"op": "remove", "path": path.as_str()}),
+            PatchOperation::Replace { path, value } => {
+                json!({"op": "replace", "path": path.as_str(), "value": value.clone()})
+            }
+            PatchOperation::Move { from, path } => {
+                json!({"op": "move", "from": from.as_str(), "path": path.as_str()})
+            }
+            PatchOperation::Copy { from, path } => {
+                json!({"op": "copy", "from": from.as_str(), "path": path.as_str()})
+            }
+            PatchOperation::Test { path, value } => {
+                json!({"op": "test", "path": path.as_str(), "value": value.clone()})
+            }
+        }
+    }
+
+    fn apply(&self, target: &mut JsonValue) -> Result<(), PatchErrorKind> {
+        match self {
+            PatchOperation::Add { path, value } => {
+                target.pointer_insert(path, value.clone())?;
+            }
+            PatchOperation::Remove { path } => {
+                target.pointer_remove(path)?;
+            }
+            PatchOperation::Replace { path, value } => {
+                *target.try_pointer_mut(path)? = value.clone();
+            }
+            PatchOperation::Move { from, path } => {
+                let from_tokens = parse_pointer(from)?;
+                let path_tokens = parse_pointer(path)?;
+                if path_tokens.len() > from_tokens.len() && path_tokens.starts_with(&from_tokens) {
+                    return Err(PatchErrorKind::MoveIntoChild);
+                }
+                let value = target.pointer_remove(from)?;
+                target.pointer_insert(path, value)?;
+            }
+            PatchOperation::Copy { from, path } => {
+                let value = target.try_pointer(from)?.clone();
+                target.pointer_insert(path, value)?;
+            }
+            PatchOperation::Test { path, value } => {
+                if target.try_pointer(path)? != value {
+                    return Err(PatchErrorKind::TestFailed);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<PointerError> for PatchErrorKind {
+    fn from(error: PointerError) -> Self {
+        PatchErrorKind::Pointer(error)
+    }
+}
+
+pub fn patch_to_json(patch: &[PatchOperation]) -> JsonValue {
+    JsonValue::Array(patch.iter().map(PatchOperation::to_json_value).collect())
+}
+
+// The operations run against a copy of the target, which only replaces the
+// target once every operation succeeded.
+#[allow(dead_code)]
+pub fn apply_patch(target: &mut JsonValue, patch: &[PatchOperation]) -> Result<(), PatchError> {
+    let mut patched = target.clone();
+    for (index, operation) in patch.iter().enumerate() {
+        operation.apply(&mut patched).map_err(|kind| PatchError {
+            index,
+            path: operation.path().to_string(),
+            kind,
+        })?;
+    }
+    *target = patched;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::json::patch::*;
+    use crate::json::tokenizer::parse_str;
+
+    fn patched(target: &str, patch: &str) -> Result<JsonValue, PatchError> {
+        let mut target = parse_str(target).unwrap();
+        let patch = parse_patch(&parse_str(patch).unwrap())?;
+        apply_patch(&mut target, &patch)?;
+        Ok(target)
+    }
+
+    #[rstest]
+    #[case(
+        "{\"a\": 1}",
+        "[{\"op\": \"add\", \"path\": \"/b\", \"value\": [1]}]",
+        "{\"a\": 1, \"b\": [1]}"
+    )]
+    #[case(
+        "{\"a\": [1, 2]}",
+        "[{\"op\": \"add\", \"path\": \"/a/1\", \"value\": 5}]",
+        "{\"a\": [1, 5, 2]}"
+    )]
+    #[case(
+        "{\"a\": 1, \"b\": 2}",
+        "[{\"op\": \"remove\", \"path\": \"/b\"}]",
+        "{\"a\": 1}"
+    )]
+    #[case(
+        "{\"a\": 1}",
+        "[{\"op\": \"replace\", \"path\": \"/a\", \"value\": \"x\"}]",
+        "{\"a\": \"x\"}"
+    )]
+    #[case(
+        "{\"a\": {\"b\": 1}, \"c\": []}",
+        "[{\"op\": \"move\", \"from\": \"/a/b\", \"path\": \"/c/-\"}]",
+        "{\"a\": {}, \"c\": [1]}"
+    )]
+    #[case(
+        "{\"a\": [1]}",
+        "[{\"op\": \"copy\", \"from\": \"/a\", \"path\": \"/b\"}]",
+        "{\"a\": [1], \"b\": [1]}"
+    )]
+    #[case(
+        "{\"a\": [1]}",
+        "[{\"op\": \"test\", \"path\": \"/a/0\", \"value\": 1}]",
+        "{\"a\": [1]}"
+    )]
+    #[case(
+        "{\"a\": 1}",
+        "[{\"op\": \"replace\", \"path\": \"\", \"value\": null}]",
+        "null"
+    )]
+    pub fn test_apply_patch(#[case] target: &str, #[case] patch: &str, #[case] expected: &str) {
+        assert_eq!(Ok(parse_str(expected).unwrap()), patched(target, patch));
+    }
+
+    #[rstest]
+    #[case(
+        "[{\"op\": \"test\", \"path\": \"/a\", \"value\": 2}]",
+        0,
+        "/a",
+        PatchErrorKind::TestFailed
+    )]
+    #[case(
+        "[{\"op\": \"remove\", \"path\": \"/a\"}, {\"op\": \"replace\", \"path\": \"/a\", \"value\": 2}]",
+        1,
+        "/a",
+        PatchErrorKind::Pointer(PointerError::MissingKey("a".to_string()))
+    )]
+    #[case(
+        "[{\"op\": \"move\", \"from\": \"/b\", \"path\": \"/b/c\"}]",
+        0,
+        "/b/c",
+        PatchErrorKind::MoveIntoChild
+    )]
+    #[case(
+        "[{\"op\": \"add\", \"path\": \"/b/5\", \"value\": 1}]",
+        0,
+        "/b/5",
+        PatchErrorKind::Pointer(PointerError::IndexOutOfBounds(5))
+    )]
+    #[case(
+        "[{\"op\": \"add\", \"path\": \"/c\"}]",
+        0,
+        "/c",
+        PatchErrorKind::MissingMember("value")
+    )]
+    #[case(
+        "[{\"op\": \"remove\", \"path\": \"/a\"}, {\"op\": \"frobnicate\", \"path\": \"/a\"}]",
+        1,
+        "/a",
+        PatchErrorKind::InvalidOperation
+    )]
+    pub fn test_apply_patch_errors(
+        #[case] patch: &str,
+        #[case] index: usize,
+        #[case] path: &str,
+        #[case] kind: PatchErrorKind,
+    ) {
+        assert_eq!(
+            Err(PatchError {
+                index,
+                path: path.to_string(),
+                kind
+            }),
+            patched("{\"a\": 1, \"b\": []}", patch)
+        );
+    }
+
+    #[test]
+    pub fn test_patch_to_json() {
+        let patch = parse_str(
+            "[{\"op\": \"move\", \"from\": \"/a\", \"path\": \"/b\"}, {\"op\": \"add\", \"path\": \"/c\", \"value\": [1]}]",
+        )
+        .unwrap();
+
+        assert_eq!(patch, patch_to_json(&parse_patch(&patch).unwrap()));
+    }
+
+    #[test]
+    pub fn test_apply_patch_is_atomic() {
+        let mut target = parse_str("{\"a\": 1}").unwrap();
+        let patch = parse_patch(
+            &parse_str("[{\"op\": \"add\", \"path\": \"/b\", \"value\": 2}, {\"op\": \"remove\", \"path\": \"/missing\"}]")
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert!(apply_patch(&mut target, &patch).is_err());
+        assert_eq!(parse_str("{\"a\": 1}").unwrap(), target);
+    }
+}
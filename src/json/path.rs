@@ -0,0 +1,546 @@
+use std::cmp::Ordering;
+
+use super::{pointer::to_pointer, tokenizer::JsonValue};
+
+#[derive(PartialEq, Debug)]
+pub enum Selector {
+    Child(String),
+    Wildcard,
+    Index(i64),
+    Slice {
+        start: Option<i64>,
+        end: Option<i64>,
+        step: Option<i64>,
+    },
+    Filter(Filter),
+}
+
+#[derive(PartialEq, Debug)]
+pub enum Filter {
+    Exists(Vec<String>),
+    Compare(Vec<String>, Comparison, JsonValue),
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct Segment {
+    pub descendant: bool,
+    pub selector: Selector,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct JsonPath {
+    pub segments: Vec<Segment>,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct PathMatch<'a> {
+    pub pointer: String,
+    pub value: &'a JsonValue,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum PathError {
+    UnexpectedEnd,
+    UnexpectedChar(usize, char),
+    InvalidNumber(usize),
+}
+
+struct PathParser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl PathParser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn peek_is(&self, expected: char) -> bool {
+        self.peek() == Some(expected)
+    }
+
+    fn next(&mut self) -> Result<char, PathError> {
+        let c = self.peek().ok_or(PathError::UnexpectedEnd)?;
+        self.position += 1;
+        Ok(c)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), PathError> {
+        let position = self.position;
+        match self.next()? {
+            c if c == expected => Ok(()),
+            c => Err(PathError::UnexpectedChar(position, c)),
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek_is(' ') {
+            self.position += 1;
+        }
+    }
+
+    fn parse(&mut self) -> Result<JsonPath, PathError> {
+        self.expect('$')?;
+        let mut segments = Vec::new();
+        while let Some(c) = self.peek() {
+            let descendant = self.chars[self.position..].starts_with(&['.', '.']);
+            let selector = match c {
+                '.' => {
+                    self.position += if descendant { 2 } else { 1 };
+                    match self.peek() {
+                        Some('[') if descendant => self.parse_bracket()?,
+                        Some('*') => {
+                            self.position += 1;
+                            Selector::Wildcard
+                        }
+                        _ => Selector::Child(self.parse_name()?),
+                    }
+                }
+                '[' => self.parse_bracket()?,
+                c => return Err(PathError::UnexpectedChar(self.position, c)),
+            };
+            segments.push(Segment {
+                descendant,
+                selector,
+            });
+        }
+        Ok(JsonPath { segments })
+    }
+
+    fn parse_name(&mut self) -> Result<String, PathError> {
+        let start = self.position;
+        while let Some(c) = self.peek() {
+            if !(c.is_alphanumeric() || c == '_' || c == '-') {
+                break;
+            }
+            self.position += 1;
+        }
+        if start == self.position {
+            return match self.peek() {
+                Some(c) => Err(PathError::UnexpectedChar(start, c)),
+                None => Err(PathError::UnexpectedEnd),
+            };
+        }
+        Ok(self.chars[start..self.position].iter().collect())
+    }
+
+    fn parse_quoted(&mut self) -> Result<String, PathError> {
+        let quote = self.next()?;
+        let mut result = String::new();
+        loop {
+            match self.next()? {
+                c if c == quote => return Ok(result),
+                '\\' => result.push(self.next()?),
+                c => result.push(c),
+            }
+        }
+    }
+
+    fn parse_integer(&mut self) -> Result<Option<i64>, PathError> {
+        let start = self.position;
+        if self.peek_is('-') {
+            self.position += 1;
+        }
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.position += 1;
+        }
+        if start == self.position {
+            return Ok(None);
+        }
+        let digits: String = self.chars[start..self.position].iter().collect();
+        match digits.parse() {
+            Ok(number) => Ok(Some(number)),
+            Err(_) => Err(PathError::InvalidNumber(start)),
+        }
+    }
+
+    fn parse_bracket(&mut self) -> Result<Selector, PathError> {
+        self.expect('[')?;
+        self.skip_spaces();
+        let selector = match self.peek() {
+            Some('*') => {
+                self.position += 1;
+                Selector::Wildcard
+            }
+            Some('\'') | Some('"') => Selector::Child(self.parse_quoted()?),
+            Some('?') => {
+                self.position += 1;
+                self.skip_spaces();
+                self.expect('(')?;
+                let filter = self.parse_filter()?;
+                self.skip_spaces();
+                self.expect(')')?;
+                Selector::Filter(filter)
+            }
+            _ => {
+                let start = self.parse_integer()?;
+                self.skip_spaces();
+                if !self.peek_is(':') {
+                    let position = self.position;
+                    let index = start.ok_or(match self.peek() {
+                        Some(c) => PathError::UnexpectedChar(position, c),
+                        None => PathError::UnexpectedEnd,
+                    })?;
+                    self.expect(']')?;
+                    return Ok(Selector::Index(index));
+                }
+                self.position += 1;
+                self.skip_spaces();
+                let end = self.parse_integer()?;
+                self.skip_spaces();
+                let mut step = None;
+                if self.peek_is(':') {
+                    self.position += 1;
+                    self.skip_spaces();
+                    step = self.parse_integer()?;
+                }
+                Selector::Slice { start, end, step }
+            }
+        };
+        self.skip_spaces();
+        self.expect(']')?;
+        Ok(selector)
+    }
+
+    fn parse_filter(&mut self) -> Result<Filter, PathError> {
+        self.skip_spaces();
+        self.expect('@')?;
+        let mut path = Vec::new();
+        loop {
+            match self.peek() {
+                Some('.') => {
+                    self.position += 1;
+                    path.push(self.parse_name()?);
+                }
+                Some('[') => {
+                    self.position += 1;
+                    self.skip_spaces();
+                    path.push(self.parse_quoted()?);
+                    self.skip_spaces();
+                    self.expect(']')?;
+                }
+                _ => break,
+            }
+        }
+        self.skip_spaces();
+        if self.peek_is(')') {
+            return Ok(Filter::Exists(path));
+        }
+        let comparison = self.parse_comparison()?;
+        self.skip_spaces();
+        let literal = self.parse_literal()?;
+        Ok(Filter::Compare(path, comparison, literal))
+    }
+
+    fn parse_comparison(&mut self) -> Result<Comparison, PathError> {
+        let position = self.position;
+        let first = self.next()?;
+        let followed_by_equal = self.peek_is('=');
+        if followed_by_equal {
+            self.position += 1;
+        }
+        match (first, followed_by_equal) {
+            ('=', true) => Ok(Comparison::Equal),
+            ('!', true) => Ok(Comparison::NotEqual),
+            ('<', true) => Ok(Comparison::LessOrEqual),
+            ('>', true) => Ok(Comparison::GreaterOrEqual),
+            ('<', false) => Ok(Comparison::Less),
+            ('>', false) => Ok(Comparison::Greater),
+            (c, _) => Err(PathError::UnexpectedChar(position, c)),
+        }
+    }
+
+    fn parse_literal(&mut self) -> Result<JsonValue, PathError> {
+        let start = self.position;
+        match self.peek() {
+            Some('\'') | Some('"') => return Ok(JsonValue::String(self.parse_quoted()?)),
+            None => return Err(PathError::UnexpectedEnd),
+            _ => {}
+        }
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || "-+.".contains(c))
+        {
+            self.position += 1;
+        }
+        let word: String = self.chars[start..self.position].iter().collect();
+        match word.as_str() {
+            "true" => Ok(JsonValue::TrueValue),
+            "false" => Ok(JsonValue::FalseValue),
+            "null" => Ok(JsonValue::NullValue),
+            number => number
+                .parse()
+                .map(JsonValue::Number)
+                .map_err(|_| PathError::InvalidNumber(start)),
+        }
+    }
+}
+
+pub fn parse_path(expression: &str) -> Result<JsonPath, PathError> {
+    PathParser {
+        chars: expression.chars().collect(),
+        position: 0,
+    }
+    .parse()
+}
+
+#[allow(dead_code)]
+pub fn query<'a>(value: &'a JsonValue, expression: &str) -> Result<Vec<PathMatch<'a>>, PathError> {
+    Ok(parse_path(expression)?.query(value))
+}
+
+type Node<'a> = (Vec<String>, &'a JsonValue);
+
+// Object members are visited in key order so a query always returns its
+// matches in the same order.
+fn children<'a>(path: &[String], value: &'a JsonValue) -> Vec<Node<'a>> {
+    let child_path = |token: String| {
+        let mut child = path.to_vec();
+        child.push(token);
+        child
+    };
+    match value {
+        JsonValue::Array(items) => items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| (child_path(i.to_string()), item))
+            .collect(),
+        JsonValue::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            keys.into_iter()
+                .map(|key| (child_path(key.clone()), &map[key]))
+                .collect()
+        }
+        _ => vec![],
+    }
+}
+
+fn descendants<'a>(node: Node<'a>, result: &mut Vec<Node<'a>>) {
+    let nested = children(&node.0, node.1);
+    result.push(node);
+    for child in nested {
+        descendants(child, result);
+    }
+}
+
+fn normalize_index(index: i64, len: i64) -> i64 {
+    if index < 0 {
+        index + len
+    } else {
+        index
+    }
+}
+
+fn slice_indices(
+    start: Option<i64>,
+    end: Option<i64>,
+    step: Option<i64>,
+    len: usize,
+) -> Vec<usize> {
+    let len = len as i64;
+    let step = step.unwrap_or(1);
+    let mut result = Vec::new();
+    if step > 0 {
+        let mut i = start.map_or(0, |i| normalize_index(i, len).clamp(0, len));
+        let end = end.map_or(len, |i| normalize_index(i, len).clamp(0, len));
+        while i < end {
+            result.push(i as usize);
+            let Some(next) = i.checked_add(step) else {
+                break;
+            };
+            i = next;
+        }
+    } else if step < 0 {
+        let mut i = start.map_or(len - 1, |i| normalize_index(i, len).clamp(-1, len - 1));
+        let end = end.map_or(-1, |i| normalize_index(i, len).clamp(-1, len - 1));
+        while i > end {
+            result.push(i as usize);
+            let Some(next) = i.checked_add(step) else {
+                break;
+            };
+            i = next;
+        }
+    }
+    result
+}
+
+fn compare(left: &JsonValue, right: &JsonValue) -> Option<Ordering> {
+    match (left, right) {
+        (JsonValue::Number(l), JsonValue::Number(r)) => l.partial_cmp(r),
+        (JsonValue::String(l), JsonValue::String(r)) => Some(l.cmp(r)),
+        (l, r) if l == r => Some(Ordering::Equal),
+        _ => None,
+    }
+}
+
+fn relative<'a>(value: &'a JsonValue, path: &[String]) -> Option<&'a JsonValue> {
+    path.iter().try_fold(value, |value, key| match value {
+        JsonValue::Object(map) => map.get(key),
+        _ => None,
+    })
+}
+
+impl Filter {
+    fn matches(&self, value: &JsonValue) -> bool {
+        match self {
+            Filter::Exists(path) => relative(value, path).is_some(),
+            Filter::Compare(path, comparison, literal) => {
+                let Some(found) = relative(value, path) else {
+                    return false;
+                };
+                let ordering = compare(found, literal);
+                match comparison {
+                    Comparison::Equal => found == literal,
+                    Comparison::NotEqual => found != literal,
+                    Comparison::Less => ordering == Some(Ordering::Less),
+                    Comparison::LessOrEqual => {
+                        matches!(ordering, Some(Ordering::Less | Ordering::Equal))
+                    }
+                    Comparison::Greater => ordering == Some(Ordering::Greater),
+                    Comparison::GreaterOrEqual => {
+                        matches!(ordering, Some(Ordering::Greater | Ordering::Equal))
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Selector {
+    fn select<'a>(&self, (path, value): Node<'a>, result: &mut Vec<Node<'a>>) {
+        match (self, value) {
+            (Selector::Child(key), JsonValue::Object(map)) => {
+                if let Some(child) = map.get(key) {
+                    let mut child_path = path;
+                    child_path.push(key.clone());
+                    result.push((child_path, child));
+                }
+            }
+            (Selector::Index(index), JsonValue::Array(items)) => {
+                let index = normalize_index(*index, items.len() as i64);
+                if index >= 0 && (index as usize) < items.len() {
+                    let mut child_path = path;
+                    child_path.push(index.to_string());
+                    result.push((child_path, &items[index as usize]));
+                }
+            }
+            (Selector::Slice { start, end, step }, JsonValue::Array(items)) => {
+                for i in slice_indices(*start, *end, *step, items.len()) {
+                    let mut child_path = path.clone();
+                    child_path.push(i.to_string());
+                    result.push((child_path, &items[i]));
+                }
+            }
+            (Selector::Wildcard, _) => result.extend(children(&path, value)),
+            (Selector::Filter(filter), _) => result.extend(
+                children(&path, value)
+                    .into_iter()
+                    .filter(|(_, child)| filter.matches(child)),
+            ),
+            _ => {}
+        }
+    }
+}
+
+impl JsonPath {
+    pub fn query<'a>(&self, value: &'a JsonValue) -> Vec<PathMatch<'a>> {
+        let mut nodes: Vec<Node<'a>> = vec![(vec![], value)];
+        for segment in &self.segments {
+            let mut selected = Vec::new();
+            for node in nodes {
+                if segment.descendant {
+                    let mut all = Vec::new();
+                    descendants(node, &mut all);
+                    for descendant in all {
+                        segment.selector.select(descendant, &mut selected);
+                    }
+                } else {
+                    segment.selector.select(node, &mut selected);
+                }
+            }
+            nodes = selected;
+        }
+        nodes
+            .into_iter()
+            .map(|(path, value)| PathMatch {
+                pointer: to_pointer(&path),
+                value,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::json::path::*;
+    use crate::json::tokenizer::parse_str;
+
+    const DOCUMENT: &str = "{\"pairs\": [{\"x0\": 1, \"y1\": 2}, {\"x0\": 3, \"y1\": 4}, {\"x0\": 5, \"y1\": 6, \"tag\": \"far\"}], \"meta\": {\"y1\": 7}}";
+
+    fn pointers(expression: &str) -> Vec<String> {
+        let document = parse_str(DOCUMENT).unwrap();
+        query(&document, expression)
+            .unwrap()
+            .into_iter()
+            .map(|found| found.pointer)
+            .collect()
+    }
+
+    #[rstest]
+    #[case("$", vec![""])]
+    #[case("$.pairs[*].x0", vec!["/pairs/0/x0", "/pairs/1/x0", "/pairs/2/x0"])]
+    #[case("$['meta'][\"y1\"]", vec!["/meta/y1"])]
+    #[case("$..y1", vec!["/meta/y1", "/pairs/0/y1", "/pairs/1/y1", "/pairs/2/y1"])]
+    #[case("$.pairs[-1].x0", vec!["/pairs/2/x0"])]
+    #[case("$.pairs[5]", vec![])]
+    #[case("$.pairs[0:2].x0", vec!["/pairs/0/x0", "/pairs/1/x0"])]
+    #[case("$.pairs[::-2].x0", vec!["/pairs/2/x0", "/pairs/0/x0"])]
+    #[case("$.pairs[1::9223372036854775807].x0", vec!["/pairs/1/x0"])]
+    #[case("$.pairs[-1::-9223372036854775808].x0", vec!["/pairs/2/x0"])]
+    #[case("$.pairs[?(@.x0 >= 3)].y1", vec!["/pairs/1/y1", "/pairs/2/y1"])]
+    #[case("$.pairs[?(@.tag == 'far')]", vec!["/pairs/2"])]
+    #[case("$.pairs[?(@.tag)].x0", vec!["/pairs/2/x0"])]
+    #[case("$..[?(@.y1 < 3)]", vec!["/pairs/0"])]
+    #[case("$.meta.*", vec!["/meta/y1"])]
+    pub fn test_query(#[case] expression: &str, #[case] expected: Vec<&str>) {
+        assert_eq!(expected, pointers(expression));
+    }
+
+    #[test]
+    pub fn test_query_values() {
+        let document = parse_str(DOCUMENT).unwrap();
+
+        let values: Vec<&JsonValue> = query(&document, "$.pairs[1:].x0")
+            .unwrap()
+            .into_iter()
+            .map(|found| found.value)
+            .collect();
+
+        assert_eq!(
+            vec![&JsonValue::Number(3.0), &JsonValue::Number(5.0)],
+            values
+        );
+    }
+
+    #[rstest]
+    #[case("pairs", PathError::UnexpectedChar(0, 'p'))]
+    #[case("$.pairs[", PathError::UnexpectedEnd)]
+    #[case("$.pairs[?(@.x0 ~ 1)]", PathError::UnexpectedChar(15, '~'))]
+    #[case("$.pairs[?(@.x0 == abc)]", PathError::InvalidNumber(18))]
+    pub fn test_parse_path_errors(#[case] expression: &str, #[case] expected: PathError) {
+        assert_eq!(Err(expected), parse_path(expression));
+    }
+}
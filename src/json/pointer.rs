@@ -0,0 +1,283 @@
+use std::mem;
+
+use super::tokenizer::JsonValue;
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum PointerError {
+    InvalidSyntax,
+    InvalidIndex(String),
+    IndexOutOfBounds(usize),
+    MissingKey(String),
+    NotContainer,
+    RootNotRemovable,
+}
+
+// RFC 6901: "" is the whole document, every other pointer is a list of
+// "/"-prefixed reference tokens where "~1" stands for "/" and "~0" for "~".
+pub fn parse_pointer(pointer: &str) -> Result<Vec<String>, PointerError> {
+    if pointer.is_empty() {
+        return Ok(vec![]);
+    }
+    let Some(rest) = pointer.strip_prefix('/') else {
+        return Err(PointerError::InvalidSyntax);
+    };
+    rest.split('/').map(unescape_token).collect()
+}
+
+fn unescape_token(token: &str) -> Result<String, PointerError> {
+    let mut result = String::with_capacity(token.len());
+    let mut chars = token.chars();
+    while let Some(c) = chars.next() {
+        if c != '~' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('0') => result.push('~'),
+            Some('1') => result.push('/'),
+            _ => return Err(PointerError::InvalidSyntax),
+        }
+    }
+    Ok(result)
+}
+
+pub fn escape_token(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+pub fn to_pointer(tokens: &[String]) -> String {
+    tokens
+        .iter()
+        .map(|token| format!("/{}", escape_token(token)))
+        .collect()
+}
+
+// Array indices are either "0" or digits without a leading zero. "-" names
+// the slot after the last element, which only makes sense when inserting.
+fn array_index(token: &str, len: usize, allow_end: bool) -> Result<usize, PointerError> {
+    if token == "-" && allow_end {
+        return Ok(len);
+    }
+    let is_digits = !token.is_empty() && token.bytes().all(|b| b.is_ascii_digit());
+    if !is_digits || (token.len() > 1 && token.starts_with('0')) {
+        return Err(PointerError::InvalidIndex(token.to_string()));
+    }
+    let Ok(index) = token.parse::<usize>() else {
+        return Err(PointerError::InvalidIndex(token.to_string()));
+    };
+    if index > len || (index == len && !allow_end) {
+        return Err(PointerError::IndexOutOfBounds(index));
+    }
+    Ok(index)
+}
+
+fn child<'a>(value: &'a JsonValue, token: &str) -> Result<&'a JsonValue, PointerError> {
+    match value {
+        JsonValue::Object(map) => map
+            .get(token)
+            .ok_or_else(|| PointerError::MissingKey(token.to_string())),
+        JsonValue::Array(items) => Ok(&items[array_index(token, items.len(), false)?]),
+        _ => Err(PointerError::NotContainer),
+    }
+}
+
+fn child_mut<'a>(value: &'a mut JsonValue, token: &str) -> Result<&'a mut JsonValue, PointerError> {
+    match value {
+        JsonValue::Object(map) => map
+            .get_mut(token)
+            .ok_or_else(|| PointerError::MissingKey(token.to_string())),
+        JsonValue::Array(items) => {
+            let index = array_index(token, items.len(), false)?;
+            Ok(&mut items[index])
+        }
+        _ => Err(PointerError::NotContainer),
+    }
+}
+
+impl JsonValue {
+    pub fn try_pointer(&self, pointer: &str) -> Result<&JsonValue, PointerError> {
+        parse_pointer(pointer)?
+            .iter()
+            .try_fold(self, |value, token| child(value, token))
+    }
+
+    pub fn try_pointer_mut(&mut self, pointer: &str) -> Result<&mut JsonValue, PointerError> {
+        parse_pointer(pointer)?
+            .iter()
+            .try_fold(self, |value, token| child_mut(value, token))
+    }
+
+    pub fn pointer(&self, pointer: &str) -> Option<&JsonValue> {
+        self.try_pointer(pointer).ok()
+    }
+
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut JsonValue> {
+        self.try_pointer_mut(pointer).ok()
+    }
+
+    // Follows the semantics of the JSON Patch "add" operation: object members
+    // are created or replaced, array elements are inserted before the index.
+    // Returns the member that was replaced, if any.
+    pub fn pointer_insert(
+        &mut self,
+        pointer: &str,
+        value: JsonValue,
+    ) -> Result<Option<JsonValue>, PointerError> {
+        let mut tokens = parse_pointer(pointer)?;
+        let Some(last) = tokens.pop() else {
+            return Ok(Some(mem::replace(self, value)));
+        };
+        let parent = tokens
+            .iter()
+            .try_fold(self, |value, token| child_mut(value, token))?;
+        match parent {
+            JsonValue::Object(map) => Ok(map.insert(last, value)),
+            JsonValue::Array(items) => {
+                let index = array_index(&last, items.len(), true)?;
+                items.insert(index, value);
+                Ok(None)
+            }
+            _ => Err(PointerError::NotContainer),
+        }
+    }
+
+    pub fn pointer_remove(&mut self, pointer: &str) -> Result<JsonValue, PointerError> {
+        let mut tokens = parse_pointer(pointer)?;
+        let Some(last) = tokens.pop() else {
+            return Err(PointerError::RootNotRemovable);
+        };
+        let parent = tokens
+            .iter()
+            .try_fold(self, |value, token| child_mut(value, token))?;
+        match parent {
+            JsonValue::Object(map) => map.remove(&last).ok_or(PointerError::MissingKey(last)),
+            JsonValue::Array(items) => {
+                let index = array_index(&last, items.len(), false)?;
+                Ok(items.remove(index))
+            }
+            _ => Err(PointerError::NotContainer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::json::pointer::*;
+    use crate::json::tokenizer::parse_str;
+
+    const DOCUMENT: &str = "{\"pairs\": [{\"x0\": 1, \"y0\": 2}, {\"x0\": 3, \"y0\": 4}], \"a/b\": 5, \"m~n\": 6, \"\": 7}";
+
+    #[rstest]
+    #[case("", None)]
+    #[case("/pairs/1/x0", Some(JsonValue::Number(3.0)))]
+    #[case("/a~1b", Some(JsonValue::Number(5.0)))]
+    #[case("/m~0n", Some(JsonValue::Number(6.0)))]
+    #[case("/", Some(JsonValue::Number(7.0)))]
+    #[case("/pairs/2", None)]
+    #[case("/pairs/01", None)]
+    #[case("/missing", None)]
+    #[case("pairs", None)]
+    pub fn test_pointer(#[case] pointer: &str, #[case] expected: Option<JsonValue>) {
+        let document = parse_str(DOCUMENT).unwrap();
+
+        match expected {
+            Some(value) => assert_eq!(Some(&value), document.pointer(pointer)),
+            None if pointer.is_empty() => assert_eq!(Some(&document), document.pointer(pointer)),
+            None => assert_eq!(None, document.pointer(pointer)),
+        }
+    }
+
+    #[rstest]
+    #[case("/~2", PointerError::InvalidSyntax)]
+    #[case("/pairs/x", PointerError::InvalidIndex("x".to_string()))]
+    #[case("/pairs/-", PointerError::InvalidIndex("-".to_string()))]
+    #[case("/pairs/5", PointerError::IndexOutOfBounds(5))]
+    #[case("/pairs/0/x0/y", PointerError::NotContainer)]
+    pub fn test_try_pointer_errors(#[case] pointer: &str, #[case] expected: PointerError) {
+        let document = parse_str(DOCUMENT).unwrap();
+
+        assert_eq!(Err(expected), document.try_pointer(pointer));
+    }
+
+    #[test]
+    pub fn test_pointer_mut() {
+        let mut document = parse_str(DOCUMENT).unwrap();
+
+        *document.pointer_mut("/pairs/0/y0").unwrap() = JsonValue::TrueValue;
+
+        assert_eq!(Some(&JsonValue::TrueValue), document.pointer("/pairs/0/y0"));
+    }
+
+    #[test]
+    pub fn test_pointer_insert() {
+        let mut document = parse_str("{\"list\": [1, 2]}").unwrap();
+
+        assert_eq!(
+            Ok(None),
+            document.pointer_insert("/list/0", JsonValue::Number(0.0))
+        );
+        assert_eq!(
+            Ok(None),
+            document.pointer_insert("/list/-", JsonValue::Number(3.0))
+        );
+        assert_eq!(
+            Ok(None),
+            document.pointer_insert("/new", JsonValue::NullValue)
+        );
+        assert_eq!(
+            Ok(Some(JsonValue::NullValue)),
+            document.pointer_insert("/new", JsonValue::FalseValue)
+        );
+        assert_eq!(
+            parse_str("{\"list\": [0, 1, 2, 3], \"new\": false}").unwrap(),
+            document
+        );
+    }
+
+    #[test]
+    pub fn test_pointer_insert_errors() {
+        let mut document = parse_str("{\"list\": [1, 2]}").unwrap();
+
+        assert_eq!(
+            Err(PointerError::IndexOutOfBounds(3)),
+            document.pointer_insert("/list/3", JsonValue::NullValue)
+        );
+        assert_eq!(
+            Err(PointerError::MissingKey("missing".to_string())),
+            document.pointer_insert("/missing/a", JsonValue::NullValue)
+        );
+    }
+
+    #[test]
+    pub fn test_pointer_remove() {
+        let mut document = parse_str(DOCUMENT).unwrap();
+
+        assert_eq!(Ok(JsonValue::Number(5.0)), document.pointer_remove("/a~1b"));
+        assert_eq!(
+            Ok(JsonValue::Number(1.0)),
+            document.pointer_remove("/pairs/0/x0")
+        );
+        assert_eq!(
+            Err(PointerError::MissingKey("a/b".to_string())),
+            document.pointer_remove("/a~1b")
+        );
+        assert_eq!(
+            Err(PointerError::RootNotRemovable),
+            document.pointer_remove("")
+        );
+    }
+
+    #[rstest]
+    #[case("a/b", "a~1b")]
+    #[case("m~n", "m~0n")]
+    #[case("~1", "~01")]
+    pub fn test_escape_token(#[case] token: &str, #[case] expected: &str) {
+        assert_eq!(expected, escape_token(token));
+        assert_eq!(
+            Ok(vec![token.to_string()]),
+            parse_pointer(&format!("/{expected}"))
+        );
+    }
+}
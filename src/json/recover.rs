@@ -0,0 +1,366 @@
+use std::{collections::HashMap, io::BufRead};
+
+use super::{
+    de::{too_deep, unexpected, Error, MAX_DEPTH},
+    lexer::{LexedToken, Lexer, LexerErrorKind, Token},
+    tokenizer::JsonValue,
+};
+
+// The best-effort value of a malformed document. Values that could not be
+// read are NullValue placeholders, members without a readable key are left
+// out, and every problem found is in `errors`, in document order.
+#[derive(PartialEq, Debug)]
+pub struct Recovered {
+    pub value: JsonValue,
+    pub errors: Vec<Error>,
+}
+
+fn is_boundary(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Comma | Token::EndArray | Token::EndObject | Token::EndOfInput
+    )
+}
+
+struct RecoveringParser<R> {
+    lexer: Lexer<R>,
+    pushed: Option<LexedToken>,
+    errors: Vec<Error>,
+    failed_io: bool,
+    depth: usize,
+}
+
+impl<R> RecoveringParser<R>
+where
+    R: BufRead,
+{
+    // Several ways of noticing the same problem, like a missing value right
+    // before a misplaced `}`, are reported once.
+    fn error(&mut self, error: Error) {
+        let last = self.errors.last().and_then(|last| last.position);
+        if last.is_none() || last != error.position {
+            self.errors.push(error);
+        }
+    }
+
+    // None stands for a malformed token, already reported and skipped.
+    fn next(&mut self) -> Option<LexedToken> {
+        if let Some(token) = self.pushed.take() {
+            return Some(token);
+        }
+        let error = match self.lexer.next_token() {
+            Ok(token) => return Some(token),
+            Err(error) => error,
+        };
+        let position = error.position;
+        if let LexerErrorKind::Io(_) = error.kind {
+            self.failed_io = true;
+        }
+        self.error(error.into());
+        if self.failed_io || self.lexer.skip_malformed().is_err() {
+            self.failed_io = true;
+            return Some(LexedToken {
+                token: Token::EndOfInput,
+                start: position,
+                end: position,
+            });
+        }
+        None
+    }
+
+    fn push_back(&mut self, token: LexedToken) {
+        self.pushed = Some(token);
+    }
+
+    // Skips to the next `,`, `]` or `}` at the current nesting level, or to
+    // the end of input, and leaves it to be read next.
+    fn resync(&mut self) {
+        let mut depth = 0;
+        loop {
+            let Some(token) = self.next() else {
+                continue;
+            };
+            match token.token {
+                Token::BeginArray | Token::BeginObject => depth += 1,
+                Token::EndArray | Token::EndObject if depth > 0 => depth -= 1,
+                Token::EndOfInput => return self.push_back(token),
+                ref boundary if depth == 0 && is_boundary(boundary) => {
+                    return self.push_back(token)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Skips the rest of a container whose opening bracket was just read,
+    // including its closing bracket.
+    fn skip_nested(&mut self) {
+        let mut depth = 1;
+        while depth > 0 {
+            let Some(token) = self.next() else {
+                continue;
+            };
+            match token.token {
+                Token::BeginArray | Token::BeginObject => depth += 1,
+                Token::EndArray | Token::EndObject => depth -= 1,
+                Token::EndOfInput => return self.push_back(token),
+                _ => {}
+            }
+        }
+    }
+
+    // Reads up to the separator after a member or element, skipping anything
+    // that should not be there.
+    fn separator(&mut self, expected: &str) -> LexedToken {
+        loop {
+            match self.next() {
+                Some(token) if is_boundary(&token.token) => return token,
+                Some(token) => {
+                    self.error(unexpected(&token, expected));
+                    self.push_back(token);
+                    self.resync();
+                }
+                None => self.resync(),
+            }
+        }
+    }
+
+    fn value(&mut self) -> JsonValue {
+        let Some(token) = self.next() else {
+            return JsonValue::NullValue;
+        };
+        match token.token {
+            Token::BeginArray | Token::BeginObject if self.depth == MAX_DEPTH => {
+                self.error(too_deep(&token));
+                self.skip_nested();
+                JsonValue::NullValue
+            }
+            Token::BeginArray | Token::BeginObject => {
+                self.depth += 1;
+                let nested = if token.token == Token::BeginArray {
+                    self.array()
+                } else {
+                    self.object()
+                };
+                self.depth -= 1;
+                nested
+            }
+            Token::String(str) => JsonValue::String(str),
+            Token::Number(number) => JsonValue::Number(number),
+            Token::TrueValue => JsonValue::TrueValue,
+            Token::FalseValue => JsonValue::FalseValue,
+            Token::NullValue => JsonValue::NullValue,
+            _ => {
+                self.error(unexpected(&token, "value"));
+                if is_boundary(&token.token) {
+                    self.push_back(token);
+                }
+                JsonValue::NullValue
+            }
+        }
+    }
+
+    // Returns false when the container has ended. A closer of the wrong kind
+    // or the end of input ends it too but is left for the enclosing one.
+    fn close(&mut self, separator: LexedToken, close: Token, expected: &str) -> bool {
+        match separator.token {
+            Token::Comma => return true,
+            ref token if *token == close => {}
+            _ => {
+                self.error(unexpected(&separator, expected));
+                self.push_back(separator);
+            }
+        }
+        false
+    }
+
+    fn array(&mut self) -> JsonValue {
+        let mut values = Vec::new();
+        if let Some(token) = self.next() {
+            if token.token == Token::EndArray {
+                return JsonValue::Array(values);
+            }
+            self.push_back(token);
+        }
+        loop {
+            values.push(self.value());
+            let separator = self.separator("`,` or `]`");
+            if !self.close(separator, Token::EndArray, "`,` or `]`") {
+                return JsonValue::Array(values);
+            }
+        }
+    }
+
+    fn object(&mut self) -> JsonValue {
+        let mut members = HashMap::new();
+        if let Some(token) = self.next() {
+            if token.token == Token::EndObject {
+                return JsonValue::Object(members);
+            }
+            self.push_back(token);
+        }
+        loop {
+            if let Some((key, value)) = self.member() {
+                members.insert(key, value);
+            }
+            let separator = self.separator("`,` or `}`");
+            if !self.close(separator, Token::EndObject, "`,` or `}`") {
+                return JsonValue::Object(members);
+            }
+        }
+    }
+
+    // A member with a missing colon is still read, one without a key is
+    // skipped up to its separator.
+    fn member(&mut self) -> Option<(String, JsonValue)> {
+        let key = match self.next() {
+            Some(LexedToken {
+                token: Token::String(key),
+                ..
+            }) => key,
+            Some(token) => {
+                self.error(unexpected(&token, "string"));
+                self.push_back(token);
+                self.resync();
+                return None;
+            }
+            None => {
+                self.resync();
+                return None;
+            }
+        };
+        if let Some(token) = self.next() {
+            if token.token != Token::Colon {
+                self.error(unexpected(&token, "`:`"));
+                self.push_back(token);
+            }
+        }
+        Some((key, self.value()))
+    }
+}
+
+// Parses as much of a document as can be made sense of instead of stopping
+// at the first error. After an error it resumes at the next `,`, `]` or `}`
+// of the same container, so one bad element does not hide the others.
+pub fn parse_recovering<R>(reader: R) -> Recovered
+where
+    R: BufRead,
+{
+    let mut parser = RecoveringParser {
+        lexer: Lexer::new(reader),
+        pushed: None,
+        errors: Vec::new(),
+        failed_io: false,
+        depth: 0,
+    };
+    let value = parser.value();
+    loop {
+        match parser.next() {
+            Some(token) if token.token == Token::EndOfInput => break,
+            Some(token) => {
+                parser.error(unexpected(&token, "end of input"));
+                break;
+            }
+            None => continue,
+        }
+    }
+    Recovered {
+        value,
+        errors: parser.errors,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::json::de;
+    use crate::json::recover::*;
+
+    fn recover(input: &str) -> (JsonValue, Vec<u64>) {
+        let recovered = parse_recovering(input.as_bytes());
+        let offsets = recovered
+            .errors
+            .iter()
+            .map(|error| error.position.unwrap().offset)
+            .collect();
+        (recovered.value, offsets)
+    }
+
+    #[rstest]
+    #[case("[1, tru, 3]", "[1, null, 3]", vec![7])]
+    #[case("[1 2, 3]", "[1, 3]", vec![3])]
+    #[case("[1, 2", "[1, 2]", vec![5])]
+    #[case("[1, , 3]", "[1, null, 3]", vec![4])]
+    #[case("[1, [2, 3}, 4]", "[1, [2, 3]]", vec![9])]
+    #[case("{\"a\" 1, \"b\": 2}", "{\"a\": 1, \"b\": 2}", vec![5])]
+    #[case("{\"a\": 1, 2: 3, \"b\": 2}", "{\"a\": 1, \"b\": 2}", vec![9])]
+    #[case("{\"a\": [1, }, \"b\": true}", "{\"a\": [1, null]}", vec![10, 11])]
+    #[case("{\"a\": 1, \"b\": @}", "{\"a\": 1, \"b\": null}", vec![14])]
+    #[case("[1] 2", "[1]", vec![4])]
+    #[case("", "null", vec![0])]
+    pub fn test_parse_recovering(
+        #[case] input: &str,
+        #[case] expected: &str,
+        #[case] offsets: Vec<u64>,
+    ) {
+        assert_eq!(
+            (de::from_str::<JsonValue>(expected).unwrap(), offsets),
+            recover(input)
+        );
+    }
+
+    #[test]
+    pub fn test_parse_recovering_collects_every_error() {
+        let input = "{\"pairs\": [\n  {\"x0\": 1.5e, \"y0\": 2},\n  {\"x0\": 3 \"y0\": 4},\n  {\"x0\": [5, 6}\n]}";
+
+        let recovered = parse_recovering(input.as_bytes());
+
+        assert_eq!(
+            vec![
+                "invalid number at line 2 column 10 (byte 21)",
+                "expected `,` or `}` but found string at line 3 column 12 (byte 48)",
+                "expected `,` or `]` but found `}` at line 4 column 15 (byte 72)",
+            ],
+            recovered
+                .errors
+                .iter()
+                .map(Error::to_string)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            de::from_str::<JsonValue>(
+                "{\"pairs\": [{\"x0\": null, \"y0\": 2}, {\"x0\": 3}, {\"x0\": [5, 6]}]}"
+            )
+            .unwrap(),
+            recovered.value
+        );
+    }
+
+    #[test]
+    pub fn test_parse_recovering_nesting_limit() {
+        let input = "[".repeat(200_000) + &"]".repeat(200_000) + "[";
+        let expected = "[".repeat(MAX_DEPTH) + "null" + &"]".repeat(MAX_DEPTH);
+
+        assert_eq!(
+            (
+                de::from_str::<JsonValue>(&expected).unwrap(),
+                vec![MAX_DEPTH as u64, 400_000]
+            ),
+            recover(&input)
+        );
+    }
+
+    #[test]
+    pub fn test_parse_recovering_valid_document() {
+        let input = "{\"pairs\": [{\"x0\": 1.5, \"y0\": -2e3}], \"ok\": [true, false, null]}";
+
+        assert_eq!(
+            Recovered {
+                value: de::from_str(input).unwrap(),
+                errors: vec![],
+            },
+            parse_recovering(input.as_bytes())
+        );
+    }
+}
@@ -0,0 +1,297 @@
+use std::fmt;
+
+use super::{de, pointer::escape_token, tokenizer::JsonValue};
+
+// Shape of the files written by generator::generate. The generator does not
+// clamp coordinates to valid latitudes and longitudes, so no ranges are set.
+const PAIRS_SCHEMA: &str = r#"{
+    "type": "object",
+    "required": ["pairs"],
+    "additionalProperties": false,
+    "properties": {
+        "pairs": {
+            "type": "array",
+            "items": {
+                "type": "object",
+                "required": ["x0", "x1", "y0", "y1"],
+                "additionalProperties": false,
+                "properties": {
+                    "x0": {"type": "number"},
+                    "x1": {"type": "number"},
+                    "y0": {"type": "number"},
+                    "y1": {"type": "number"}
+                }
+            }
+        }
+    }
+}"#;
+
+#[derive(PartialEq, Debug)]
+pub enum ViolationKind {
+    Type {
+        expected: String,
+        found: &'static str,
+    },
+    Required(String),
+    AdditionalProperty(String),
+    Minimum(f64),
+    Maximum(f64),
+    Enum,
+    Rejected,
+    InvalidSchema(&'static str),
+}
+
+#[derive(PartialEq, Debug)]
+pub struct Violation {
+    pub pointer: String,
+    pub kind: ViolationKind,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.pointer)?;
+        match &self.kind {
+            ViolationKind::Type { expected, found } => {
+                write!(f, "expected {}, found {}", expected, found)
+            }
+            ViolationKind::Required(key) => write!(f, "missing required property \"{}\"", key),
+            ViolationKind::AdditionalProperty(key) => {
+                write!(f, "property \"{}\" is not allowed", key)
+            }
+            ViolationKind::Minimum(minimum) => write!(f, "must be at least {}", minimum),
+            ViolationKind::Maximum(maximum) => write!(f, "must be at most {}", maximum),
+            ViolationKind::Enum => write!(f, "is not one of the allowed values"),
+            ViolationKind::Rejected => write!(f, "is not allowed"),
+            ViolationKind::InvalidSchema(keyword) => write!(f, "invalid \"{}\" in schema", keyword),
+        }
+    }
+}
+
+pub fn pairs_schema() -> JsonValue {
+    de::from_str(PAIRS_SCHEMA).unwrap()
+}
+
+pub fn type_name(value: &JsonValue) -> &'static str {
+    match value {
+        JsonValue::Object(_) => "object",
+        JsonValue::Array(_) => "array",
+        JsonValue::String(_) => "string",
+        JsonValue::Number(_) => "number",
+        JsonValue::TrueValue | JsonValue::FalseValue => "boolean",
+        JsonValue::NullValue => "null",
+    }
+}
+
+fn has_type(value: &JsonValue, expected: &str) -> bool {
+    match (expected, value) {
+        ("integer", JsonValue::Number(number)) => number.fract() == 0.0,
+        (expected, value) => expected == type_name(value),
+    }
+}
+
+struct Validator {
+    violations: Vec<Violation>,
+}
+
+impl Validator {
+    fn report(&mut self, pointer: &str, kind: ViolationKind) {
+        self.violations.push(Violation {
+            pointer: pointer.to_string(),
+            kind,
+        });
+    }
+
+    fn validate(&mut self, schema: &JsonValue, value: &JsonValue, pointer: &str) {
+        // true and {} accept everything, false rejects everything.
+        let schema = match schema {
+            JsonValue::Object(schema) => schema,
+            JsonValue::TrueValue => return,
+            JsonValue::FalseValue => {
+                self.report(pointer, ViolationKind::Rejected);
+                return;
+            }
+            _ => {
+                self.report(pointer, ViolationKind::InvalidSchema("schema"));
+                return;
+            }
+        };
+
+        if let Some(expected) = schema.get("type") {
+            let expected: Vec<&str> = match expected {
+                JsonValue::String(name) => vec![name.as_str()],
+                JsonValue::Array(names) => names.iter().filter_map(JsonValue::as_str).collect(),
+                _ => {
+                    self.report(pointer, ViolationKind::InvalidSchema("type"));
+                    vec![]
+                }
+            };
+            if !expected.is_empty() && !expected.iter().any(|name| has_type(value, name)) {
+                self.report(
+                    pointer,
+                    ViolationKind::Type {
+                        expected: expected.join(" or "),
+                        found: type_name(value),
+                    },
+                );
+                return;
+            }
+        }
+
+        if let Some(allowed) = schema.get("enum") {
+            match allowed {
+                JsonValue::Array(allowed) if !allowed.contains(value) => {
+                    self.report(pointer, ViolationKind::Enum)
+                }
+                JsonValue::Array(_) => {}
+                _ => self.report(pointer, ViolationKind::InvalidSchema("enum")),
+            }
+        }
+
+        if let JsonValue::Number(number) = value {
+            match schema.get("minimum") {
+                Some(JsonValue::Number(minimum)) if number < minimum => {
+                    self.report(pointer, ViolationKind::Minimum(*minimum))
+                }
+                Some(JsonValue::Number(_)) | None => {}
+                Some(_) => self.report(pointer, ViolationKind::InvalidSchema("minimum")),
+            }
+            match schema.get("maximum") {
+                Some(JsonValue::Number(maximum)) if number > maximum => {
+                    self.report(pointer, ViolationKind::Maximum(*maximum))
+                }
+                Some(JsonValue::Number(_)) | None => {}
+                Some(_) => self.report(pointer, ViolationKind::InvalidSchema("maximum")),
+            }
+        }
+
+        if let Some(items) = value.as_array() {
+            if let Some(items_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    self.validate(items_schema, item, &format!("{}/{}", pointer, i));
+                }
+            }
+        }
+
+        if let Some(members) = value.as_object() {
+            match schema.get("required") {
+                Some(JsonValue::Array(required)) => {
+                    for key in required {
+                        match key {
+                            JsonValue::String(key) if !members.contains_key(key) => {
+                                self.report(pointer, ViolationKind::Required(key.clone()))
+                            }
+                            JsonValue::String(_) => {}
+                            _ => self.report(pointer, ViolationKind::InvalidSchema("required")),
+                        }
+                    }
+                }
+                Some(_) => self.report(pointer, ViolationKind::InvalidSchema("required")),
+                None => {}
+            }
+
+            let properties = match schema.get("properties") {
+                Some(JsonValue::Object(properties)) => Some(properties),
+                Some(_) => {
+                    self.report(pointer, ViolationKind::InvalidSchema("properties"));
+                    None
+                }
+                None => None,
+            };
+            let mut keys: Vec<&String> = members.keys().collect();
+            keys.sort();
+            for key in keys {
+                let member_pointer = format!("{}/{}", pointer, escape_token(key));
+                match (
+                    properties.and_then(|p| p.get(key)),
+                    schema.get("additionalProperties"),
+                ) {
+                    (Some(property_schema), _) => {
+                        self.validate(property_schema, &members[key], &member_pointer)
+                    }
+                    (None, Some(JsonValue::FalseValue)) => {
+                        self.report(pointer, ViolationKind::AdditionalProperty(key.clone()))
+                    }
+                    (None, Some(additional)) => {
+                        self.validate(additional, &members[key], &member_pointer)
+                    }
+                    (None, None) => {}
+                }
+            }
+        }
+    }
+}
+
+// Collects every violation instead of stopping at the first one. Object
+// members are checked in key order so the report is stable.
+pub fn validate(schema: &JsonValue, value: &JsonValue) -> Vec<Violation> {
+    let mut validator = Validator { violations: vec![] };
+    validator.validate(schema, value, "");
+    validator.violations
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::json::schema::*;
+    use crate::json::tokenizer::parse_str;
+
+    fn messages(schema: &str, value: &str) -> Vec<String> {
+        validate(&parse_str(schema).unwrap(), &parse_str(value).unwrap())
+            .iter()
+            .map(|violation| violation.to_string())
+            .collect()
+    }
+
+    #[test]
+    pub fn test_pairs_schema_accepts_generated_shape() {
+        let value =
+            parse_str("{\"pairs\": [{\"x0\": 1, \"x1\": 2, \"y0\": 3, \"y1\": 4}]}").unwrap();
+
+        assert_eq!(Vec::<Violation>::new(), validate(&pairs_schema(), &value));
+    }
+
+    #[test]
+    pub fn test_pairs_schema_collects_every_violation() {
+        let value = parse_str(
+            "{\"pairs\": [{\"x0\": 1, \"x1\": 2, \"y0\": 3, \"y1\": 4}, {\"x0\": \"1\", \"x1\": 2, \"y0\": 3, \"z\": 4}], \"extra\": 1}",
+        )
+        .unwrap();
+
+        let messages: Vec<String> = validate(&pairs_schema(), &value)
+            .iter()
+            .map(|violation| violation.to_string())
+            .collect();
+
+        assert_eq!(
+            vec![
+                ": property \"extra\" is not allowed",
+                "/pairs/1: missing required property \"y1\"",
+                "/pairs/1/x0: expected number, found string",
+                "/pairs/1: property \"z\" is not allowed",
+            ],
+            messages
+        );
+    }
+
+    #[rstest]
+    #[case("{\"type\": \"integer\"}", "1.5", vec![": expected integer, found number"])]
+    #[case("{\"type\": \"integer\"}", "2", vec![])]
+    #[case("{\"type\": [\"string\", \"null\"]}", "null", vec![])]
+    #[case("{\"type\": [\"string\", \"null\"]}", "true", vec![": expected string or null, found boolean"])]
+    #[case("{\"minimum\": -90, \"maximum\": 90}", "-91", vec![": must be at least -90"])]
+    #[case("{\"minimum\": -90, \"maximum\": 90}", "90", vec![])]
+    #[case("{\"maximum\": 90}", "\"x\"", vec![])]
+    #[case("{\"enum\": [1, \"a\", null]}", "\"a\"", vec![])]
+    #[case("{\"enum\": [1, \"a\", null]}", "2", vec![": is not one of the allowed values"])]
+    #[case("{\"items\": {\"type\": \"number\"}}", "[1, \"2\", 3]", vec!["/1: expected number, found string"])]
+    #[case(
+        "{\"additionalProperties\": {\"type\": \"boolean\"}, \"properties\": {\"a\": {}}}",
+        "{\"a\": 1, \"b\": true, \"c\": 2}",
+        vec!["/c: expected boolean, found number"]
+    )]
+    #[case("{\"type\": 5}", "1", vec![": invalid \"type\" in schema"])]
+    pub fn test_validate(#[case] schema: &str, #[case] value: &str, #[case] expected: Vec<&str>) {
+        assert_eq!(expected, messages(schema, value));
+    }
+}
@@ -0,0 +1,839 @@
+use std::{
+    borrow::Cow,
+    fmt,
+    io::{self, Write},
+    ops::Range,
+};
+
+use serde::ser::{self, Serialize};
+
+use super::writer::{write_number, write_string};
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum Error {
+    Io(io::ErrorKind),
+    KeyMustBeString,
+    Custom(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(kind) => write!(f, "write failed: {:?}", kind),
+            Error::KeyMustBeString => write!(f, "object keys must be strings"),
+            Error::Custom(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        Error::Custom(message.to_string())
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error.kind())
+    }
+}
+
+// Writes serde data through the same functions as writer::write_value. Every
+// number becomes an f64 like in JsonValue, and object members are written in
+// key order, so a value serialized here is byte-identical to the same value
+// written as a JsonValue.
+pub struct Serializer<W> {
+    writer: W,
+}
+
+impl<W> Serializer<W>
+where
+    W: Write,
+{
+    pub fn new(writer: W) -> Serializer<W> {
+        Serializer { writer }
+    }
+
+    fn begin_variant(&mut self, variant: &str) -> Result<(), Error> {
+        self.writer.write_all(b"{")?;
+        write_string(&mut self.writer, variant)?;
+        self.writer.write_all(b":")?;
+        Ok(())
+    }
+}
+
+pub fn to_writer<W, T>(writer: W, value: &T) -> Result<(), Error>
+where
+    W: Write,
+    T: Serialize + ?Sized,
+{
+    value.serialize(&mut Serializer::new(writer))
+}
+
+pub fn to_vec<T>(value: &T) -> Result<Vec<u8>, Error>
+where
+    T: Serialize + ?Sized,
+{
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, value)?;
+    Ok(buffer)
+}
+
+#[allow(dead_code)]
+pub fn to_string<T>(value: &T) -> Result<String, Error>
+where
+    T: Serialize + ?Sized,
+{
+    Ok(String::from_utf8(to_vec(value)?).unwrap())
+}
+
+impl<'a, W> ser::Serializer for &'a mut Serializer<W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Array<'a, W>;
+    type SerializeTuple = Array<'a, W>;
+    type SerializeTupleStruct = Array<'a, W>;
+    type SerializeTupleVariant = Array<'a, W>;
+    type SerializeMap = Object<'a, W>;
+    type SerializeStruct = Object<'a, W>;
+    type SerializeStructVariant = Object<'a, W>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        self.writer.write_all(if v { b"true" } else { b"false" })?;
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        write_number(&mut self.writer, v)?;
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        write_string(&mut self.writer, v)?;
+        Ok(())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        let mut array = ser::Serializer::serialize_seq(self, Some(v.len()))?;
+        for b in v {
+            ser::SerializeSeq::serialize_element(&mut array, b)?;
+        }
+        ser::SerializeSeq::end(array)
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        self.writer.write_all(b"null")?;
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.begin_variant(variant)?;
+        value.serialize(&mut *self)?;
+        self.writer.write_all(b"}")?;
+        Ok(())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Array<'a, W>, Error> {
+        self.writer.write_all(b"[")?;
+        Ok(Array {
+            ser: self,
+            first: true,
+            variant: false,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Array<'a, W>, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Array<'a, W>, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Array<'a, W>, Error> {
+        self.begin_variant(variant)?;
+        let mut array = self.serialize_seq(Some(len))?;
+        array.variant = true;
+        Ok(array)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Object<'a, W>, Error> {
+        Ok(Object {
+            ser: self,
+            buffer: Vec::new(),
+            members: Vec::new(),
+            key: None,
+            variant: false,
+            direct: false,
+        })
+    }
+
+    // A single field is always in order, so the root of a document like
+    // `{"pairs": [...]}` is never buffered whole.
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Object<'a, W>, Error> {
+        let direct = len <= 1;
+        if direct {
+            self.writer.write_all(b"{")?;
+        }
+        let mut object = self.serialize_map(Some(len))?;
+        object.direct = direct;
+        Ok(object)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Object<'a, W>, Error> {
+        self.begin_variant(variant)?;
+        let mut object = self.serialize_struct(name, len)?;
+        object.variant = true;
+        Ok(object)
+    }
+}
+
+pub struct Array<'a, W> {
+    ser: &'a mut Serializer<W>,
+    first: bool,
+    variant: bool,
+}
+
+impl<W> Array<'_, W>
+where
+    W: Write,
+{
+    fn element<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        if !self.first {
+            self.ser.writer.write_all(b",")?;
+        }
+        self.first = false;
+        value.serialize(&mut *self.ser)
+    }
+
+    fn finish(self) -> Result<(), Error> {
+        self.ser.writer.write_all(b"]")?;
+        if self.variant {
+            self.ser.writer.write_all(b"}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<W> ser::SerializeSeq for Array<'_, W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<W> ser::SerializeTuple for Array<'_, W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<W> ser::SerializeTupleStruct for Array<'_, W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<W> ser::SerializeTupleVariant for Array<'_, W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+// Members are serialized one after another into a buffer owned by the object
+// and written out sorted by key once the object is complete, the same order
+// writer::write_value uses. Structs with a single field are written directly.
+pub struct Object<'a, W> {
+    ser: &'a mut Serializer<W>,
+    buffer: Vec<u8>,
+    members: Vec<(Cow<'static, str>, Range<usize>)>,
+    key: Option<String>,
+    variant: bool,
+    direct: bool,
+}
+
+impl<W> Object<'_, W>
+where
+    W: Write,
+{
+    fn member<T>(&mut self, key: Cow<'static, str>, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        let start = self.buffer.len();
+        value.serialize(&mut Serializer::new(&mut self.buffer))?;
+        self.members.push((key, start..self.buffer.len()));
+        Ok(())
+    }
+
+    fn field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        if !self.direct {
+            return self.member(Cow::Borrowed(key), value);
+        }
+        let writer = &mut self.ser.writer;
+        if !self.members.is_empty() {
+            writer.write_all(b",")?;
+        }
+        write_string(writer, key)?;
+        writer.write_all(b":")?;
+        self.members.push((Cow::Borrowed(key), 0..0));
+        value.serialize(&mut *self.ser)
+    }
+
+    fn finish(mut self) -> Result<(), Error> {
+        if self.direct {
+            self.ser.writer.write_all(b"}")?;
+            if self.variant {
+                self.ser.writer.write_all(b"}")?;
+            }
+            return Ok(());
+        }
+        self.members.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let writer = &mut self.ser.writer;
+        writer.write_all(b"{")?;
+        for (i, (key, value)) in self.members.iter().enumerate() {
+            if i > 0 {
+                writer.write_all(b",")?;
+            }
+            write_string(writer, key)?;
+            writer.write_all(b":")?;
+            writer.write_all(&self.buffer[value.clone()])?;
+        }
+        writer.write_all(b"}")?;
+        if self.variant {
+            writer.write_all(b"}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<W> ser::SerializeMap for Object<'_, W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.key = Some(key.serialize(MapKey)?);
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        let key = self
+            .key
+            .take()
+            .ok_or(Error::Custom("value without key".to_string()))?;
+        self.member(Cow::Owned(key), value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<W> ser::SerializeStruct for Object<'_, W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<W> ser::SerializeStructVariant for Object<'_, W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+// Object keys have to be strings. Like serde_json, integer and char keys are
+// accepted and written as their string form.
+struct MapKey;
+
+macro_rules! serialize_key_to_string {
+    ($($method: ident: $type: ty),*) => {
+        $(
+            fn $method(self, v: $type) -> Result<String, Error> {
+                Ok(v.to_string())
+            }
+        )*
+    };
+}
+
+macro_rules! reject_key {
+    ($($method: ident($($arg: ident: $type: ty),*)),*) => {
+        $(
+            fn $method(self, $(_: $type),*) -> Result<String, Error> {
+                Err(Error::KeyMustBeString)
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for MapKey {
+    type Ok = String;
+    type Error = Error;
+    type SerializeSeq = ser::Impossible<String, Error>;
+    type SerializeTuple = ser::Impossible<String, Error>;
+    type SerializeTupleStruct = ser::Impossible<String, Error>;
+    type SerializeTupleVariant = ser::Impossible<String, Error>;
+    type SerializeMap = ser::Impossible<String, Error>;
+    type SerializeStruct = ser::Impossible<String, Error>;
+    type SerializeStructVariant = ser::Impossible<String, Error>;
+
+    serialize_key_to_string!(
+        serialize_i8: i8, serialize_i16: i16, serialize_i32: i32, serialize_i64: i64,
+        serialize_u8: u8, serialize_u16: u16, serialize_u32: u32, serialize_u64: u64,
+        serialize_char: char, serialize_str: &str
+    );
+
+    reject_key!(
+        serialize_bool(v: bool),
+        serialize_f32(v: f32),
+        serialize_f64(v: f64),
+        serialize_bytes(v: &[u8]),
+        serialize_none(),
+        serialize_unit(),
+        serialize_unit_struct(name: &'static str)
+    );
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<String, Error> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_some<T>(self, _value: &T) -> Result<String, Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        Err(Error::KeyMustBeString)
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<String, Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String, Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        Err(Error::KeyMustBeString)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Err(Error::KeyMustBeString)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Error> {
+        Err(Error::KeyMustBeString)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        Err(Error::KeyMustBeString)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(Error::KeyMustBeString)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Err(Error::KeyMustBeString)
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        Err(Error::KeyMustBeString)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(Error::KeyMustBeString)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+
+    use serde::Serialize;
+
+    use crate::json::from_json::{derive_json, ToJson};
+    use crate::json::ser::*;
+    use crate::json::tokenizer::parse_str;
+    use crate::json::writer;
+
+    #[derive(Serialize)]
+    struct Pair {
+        y1: f64,
+        x0: f64,
+        y0: f64,
+        x1: f64,
+    }
+
+    derive_json!(Pair { y1, x0, y0, x1 });
+
+    #[derive(Serialize)]
+    struct Output {
+        pairs: Vec<Pair>,
+        label: Option<String>,
+        count: u32,
+    }
+
+    derive_json!(Output {
+        pairs,
+        label,
+        count
+    });
+
+    #[derive(Serialize)]
+    enum Shape {
+        Empty,
+        Circle(f64),
+        Segment(f64, f64),
+        Rectangle { width: u32, height: u32 },
+    }
+
+    #[test]
+    pub fn test_to_string_matches_writer() {
+        let output = Output {
+            pairs: vec![
+                Pair {
+                    y1: 1.5,
+                    x0: -170.25,
+                    y0: 1e-7,
+                    x1: 3.0,
+                },
+                Pair {
+                    y1: 0.1,
+                    x0: 1e300,
+                    y0: f64::NAN,
+                    x1: -0.0,
+                },
+            ],
+            label: Some("tab\tquote\"".to_string()),
+            count: 2,
+        };
+
+        assert_eq!(
+            writer::to_string(&output.to_json()),
+            to_string(&output).unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_to_string_round_trips_through_tokenizer() {
+        let value = HashMap::from([("b", vec![1, 2]), ("a", vec![])]);
+
+        let written = to_string(&value).unwrap();
+
+        assert_eq!("{\"a\":[],\"b\":[1.0,2.0]}", written);
+        assert!(parse_str(&written).is_ok());
+    }
+
+    #[test]
+    pub fn test_to_string_enums() {
+        let shapes = vec![
+            Shape::Empty,
+            Shape::Circle(2.0),
+            Shape::Segment(1.0, 2.0),
+            Shape::Rectangle {
+                width: 3,
+                height: 4,
+            },
+        ];
+
+        assert_eq!(
+            "[\"Empty\",{\"Circle\":2.0},{\"Segment\":[1.0,2.0]},{\"Rectangle\":{\"height\":4.0,\"width\":3.0}}]",
+            to_string(&shapes).unwrap()
+        );
+    }
+
+    #[derive(Serialize)]
+    struct Point {
+        x: f64,
+        y: f64,
+    }
+
+    derive_json!(Point { x, y });
+
+    #[derive(Serialize)]
+    struct Shapes {
+        points: Vec<Point>,
+        pairs: Vec<Pair>,
+    }
+
+    derive_json!(Shapes { points, pairs });
+
+    // Named like Point but with its fields in the opposite order.
+    mod reversed {
+        use serde::Serialize;
+
+        #[derive(Serialize)]
+        pub struct Point {
+            pub y: f64,
+            pub x: f64,
+        }
+    }
+
+    #[test]
+    pub fn test_to_string_sorts_fields_of_each_value() {
+        let shapes = Shapes {
+            points: (0..3)
+                .map(|i| Point {
+                    x: i as f64,
+                    y: -i as f64,
+                })
+                .collect(),
+            pairs: (0..3)
+                .map(|i| Pair {
+                    y1: i as f64,
+                    x0: 1.0,
+                    y0: 2.0,
+                    x1: 3.0,
+                })
+                .collect(),
+        };
+        let points = (
+            Point { x: 1.0, y: 2.0 },
+            reversed::Point { y: 2.0, x: 1.0 },
+            Point { x: 1.0, y: 2.0 },
+        );
+
+        assert_eq!(
+            writer::to_string(&shapes.to_json()),
+            to_string(&shapes).unwrap()
+        );
+        assert_eq!(
+            "[{\"x\":1.0,\"y\":2.0},{\"x\":1.0,\"y\":2.0},{\"x\":1.0,\"y\":2.0}]",
+            to_string(&points).unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_to_string_map_keys() {
+        let numbers = BTreeMap::from([(10, true), (2, false)]);
+        let invalid = BTreeMap::from([(vec![1], true)]);
+
+        assert_eq!("{\"10\":true,\"2\":false}", to_string(&numbers).unwrap());
+        assert_eq!(Err(Error::KeyMustBeString), to_string(&invalid));
+    }
+}
@@ -0,0 +1,327 @@
+use std::{collections::HashMap, io::BufRead};
+
+use super::{
+    de::{too_deep, unexpected, Error, MAX_DEPTH},
+    lexer::{Lexer, ParseOptions, Position, Token},
+    pointer::parse_pointer,
+    tokenizer::JsonValue,
+};
+
+// From the first byte of a value or key to just past its last byte.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+#[derive(PartialEq, Debug)]
+enum SpanChildren {
+    Scalar,
+    Array(Vec<SpanTree>),
+    Object(HashMap<String, (Span, SpanTree)>),
+}
+
+// Mirrors the shape of a parsed JsonValue with the source location of every
+// value, and of every object key next to its value.
+#[derive(PartialEq, Debug)]
+pub struct SpanTree {
+    pub span: Span,
+    children: SpanChildren,
+}
+
+impl SpanTree {
+    // Returns the node a pointer refers to and, for object members, the span
+    // of its key.
+    fn find(&self, pointer: &str) -> Option<(&SpanTree, Option<Span>)> {
+        let mut node = self;
+        let mut key_span = None;
+        for token in parse_pointer(pointer).ok()? {
+            (node, key_span) = match &node.children {
+                SpanChildren::Object(members) => {
+                    let (key_span, child) = members.get(&token)?;
+                    (child, Some(*key_span))
+                }
+                SpanChildren::Array(items) => {
+                    let index: usize = token.parse().ok()?;
+                    if index.to_string() != token {
+                        return None;
+                    }
+                    (items.get(index)?, None)
+                }
+                SpanChildren::Scalar => return None,
+            };
+        }
+        Some((node, key_span))
+    }
+
+    pub fn value_span(&self, pointer: &str) -> Option<Span> {
+        self.find(pointer).map(|(node, _)| node.span)
+    }
+
+    #[allow(dead_code)]
+    pub fn key_span(&self, pointer: &str) -> Option<Span> {
+        self.find(pointer).and_then(|(_, key_span)| key_span)
+    }
+}
+
+struct SpanParser<R> {
+    lexer: Lexer<R>,
+    depth: usize,
+}
+
+impl<R> SpanParser<R>
+where
+    R: BufRead,
+{
+    fn value(&mut self) -> Result<(JsonValue, SpanTree), Error> {
+        let token = self.lexer.next_token()?;
+        let start = token.start;
+        let scalar = |value| {
+            let span = Span {
+                start,
+                end: token.end,
+            };
+            Ok((
+                value,
+                SpanTree {
+                    span,
+                    children: SpanChildren::Scalar,
+                },
+            ))
+        };
+        match token.token {
+            Token::BeginArray | Token::BeginObject if self.depth == MAX_DEPTH => {
+                Err(too_deep(&token))
+            }
+            Token::BeginArray | Token::BeginObject => {
+                self.depth += 1;
+                let nested = if token.token == Token::BeginArray {
+                    self.array(start)
+                } else {
+                    self.object(start)
+                };
+                self.depth -= 1;
+                nested
+            }
+            Token::String(ref str) => scalar(JsonValue::String(str.clone())),
+            Token::Number(number) => scalar(JsonValue::Number(number)),
+            Token::TrueValue => scalar(JsonValue::TrueValue),
+            Token::FalseValue => scalar(JsonValue::FalseValue),
+            Token::NullValue => scalar(JsonValue::NullValue),
+            _ => Err(unexpected(&token, "value")),
+        }
+    }
+
+    // Whether the comma just read is a trailing one, allowed by the options
+    // and followed by `close`.
+    fn trailing_comma(&mut self, close: Token) -> Result<bool, Error> {
+        Ok(self.lexer.options().trailing_commas && self.lexer.peek()?.token == close)
+    }
+
+    fn array(&mut self, start: Position) -> Result<(JsonValue, SpanTree), Error> {
+        let mut values = Vec::new();
+        let mut spans = Vec::new();
+        let mut end = self.lexer.peek()?.end;
+        if self.lexer.peek()?.token == Token::EndArray {
+            self.lexer.next_token()?;
+        } else {
+            loop {
+                let (value, span) = self.value()?;
+                values.push(value);
+                spans.push(span);
+                let separator = self.lexer.next_token()?;
+                match separator.token {
+                    Token::Comma if self.trailing_comma(Token::EndArray)? => {
+                        end = self.lexer.next_token()?.end;
+                        break;
+                    }
+                    Token::Comma => continue,
+                    Token::EndArray => {
+                        end = separator.end;
+                        break;
+                    }
+                    _ => return Err(unexpected(&separator, "`,` or `]`")),
+                }
+            }
+        }
+        Ok((
+            JsonValue::Array(values),
+            SpanTree {
+                span: Span { start, end },
+                children: SpanChildren::Array(spans),
+            },
+        ))
+    }
+
+    fn object(&mut self, start: Position) -> Result<(JsonValue, SpanTree), Error> {
+        let mut values = HashMap::new();
+        let mut spans = HashMap::new();
+        let mut end = self.lexer.peek()?.end;
+        if self.lexer.peek()?.token == Token::EndObject {
+            self.lexer.next_token()?;
+        } else {
+            loop {
+                let token = self.lexer.next_token()?;
+                let (Token::String(key) | Token::Identifier(key)) = token.token else {
+                    return Err(unexpected(&token, "string"));
+                };
+                let key_span = Span {
+                    start: token.start,
+                    end: token.end,
+                };
+                let colon = self.lexer.next_token()?;
+                if colon.token != Token::Colon {
+                    return Err(unexpected(&colon, "`:`"));
+                }
+                let (value, span) = self.value()?;
+                values.insert(key.clone(), value);
+                spans.insert(key, (key_span, span));
+                let separator = self.lexer.next_token()?;
+                match separator.token {
+                    Token::Comma if self.trailing_comma(Token::EndObject)? => {
+                        end = self.lexer.next_token()?.end;
+                        break;
+                    }
+                    Token::Comma => continue,
+                    Token::EndObject => {
+                        end = separator.end;
+                        break;
+                    }
+                    _ => return Err(unexpected(&separator, "`,` or `}`")),
+                }
+            }
+        }
+        Ok((
+            JsonValue::Object(values),
+            SpanTree {
+                span: Span { start, end },
+                children: SpanChildren::Object(spans),
+            },
+        ))
+    }
+}
+
+// Parses a whole document and records where every value and key came from,
+// so that problems found later in the tree can be reported by location.
+pub fn parse_with_spans<R>(reader: R, options: ParseOptions) -> Result<(JsonValue, SpanTree), Error>
+where
+    R: BufRead,
+{
+    let mut parser = SpanParser {
+        lexer: Lexer::with_options(reader, options),
+        depth: 0,
+    };
+    let result = parser.value()?;
+    let token = parser.lexer.next_token()?;
+    if token.token != Token::EndOfInput {
+        return Err(unexpected(&token, "end of input"));
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::json::de;
+    use crate::json::spans::*;
+
+    const DOCUMENT: &str = "{\"pairs\": [\n  {\"x0\": 1.5, \"a/b\": \"é\"},\n  []\n], \"n\": null}";
+
+    fn offsets(span: Option<Span>) -> Option<(u64, u64)> {
+        span.map(|span| (span.start.offset, span.end.offset))
+    }
+
+    #[test]
+    pub fn test_parse_with_spans_value() {
+        let (value, _) = parse_with_spans(DOCUMENT.as_bytes(), ParseOptions::default()).unwrap();
+
+        assert_eq!(de::from_str::<JsonValue>(DOCUMENT).unwrap(), value);
+    }
+
+    #[rstest]
+    #[case("", Some((0, 58)))]
+    #[case("/pairs", Some((10, 46)))]
+    #[case("/pairs/0", Some((14, 38)))]
+    #[case("/pairs/0/x0", Some((21, 24)))]
+    #[case("/pairs/0/a~1b", Some((33, 37)))]
+    #[case("/pairs/1", Some((42, 44)))]
+    #[case("/n", Some((53, 57)))]
+    #[case("/pairs/2", None)]
+    #[case("/pairs/01", None)]
+    #[case("/n/0", None)]
+    #[case("pairs", None)]
+    pub fn test_value_span(#[case] pointer: &str, #[case] expected: Option<(u64, u64)>) {
+        let (_, spans) = parse_with_spans(DOCUMENT.as_bytes(), ParseOptions::default()).unwrap();
+
+        assert_eq!(expected, offsets(spans.value_span(pointer)));
+    }
+
+    #[rstest]
+    #[case("/pairs", Some((1, 8)))]
+    #[case("/pairs/0/a~1b", Some((26, 31)))]
+    #[case("/pairs/0", None)]
+    #[case("", None)]
+    pub fn test_key_span(#[case] pointer: &str, #[case] expected: Option<(u64, u64)>) {
+        let (_, spans) = parse_with_spans(DOCUMENT.as_bytes(), ParseOptions::default()).unwrap();
+
+        assert_eq!(expected, offsets(spans.key_span(pointer)));
+    }
+
+    #[test]
+    pub fn test_span_line_and_column() {
+        let (_, spans) = parse_with_spans(DOCUMENT.as_bytes(), ParseOptions::default()).unwrap();
+        let span = spans.value_span("/pairs/0/a~1b").unwrap();
+
+        assert_eq!((2, 22), (span.start.line, span.start.column));
+        assert_eq!((2, 25), (span.end.line, span.end.column));
+    }
+
+    #[rstest]
+    #[case(
+        "[1, 2",
+        "expected `,` or `]` but found end of input at line 1 column 6 (byte 5)"
+    )]
+    #[case(
+        "{\"a\" 1}",
+        "expected `:` but found number at line 1 column 6 (byte 5)"
+    )]
+    #[case(
+        "[] []",
+        "expected end of input but found `[` at line 1 column 4 (byte 3)"
+    )]
+    pub fn test_parse_with_spans_errors(#[case] input: &str, #[case] message: &str) {
+        assert_eq!(
+            message,
+            parse_with_spans(input.as_bytes(), ParseOptions::default())
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    pub fn test_parse_with_spans_lenient() {
+        let input = "{pairs: [{'x0': 1.5,},], // comment\n n: null,}";
+
+        let (value, spans) = parse_with_spans(input.as_bytes(), ParseOptions::lenient()).unwrap();
+
+        assert_eq!(
+            de::from_str::<JsonValue>("{\"pairs\": [{\"x0\": 1.5}], \"n\": null}").unwrap(),
+            value
+        );
+        assert_eq!(Some((0, 46)), offsets(spans.value_span("")));
+        assert_eq!(Some((8, 23)), offsets(spans.value_span("/pairs")));
+        assert_eq!(Some((1, 6)), offsets(spans.key_span("/pairs")));
+        assert!(parse_with_spans(input.as_bytes(), ParseOptions::default()).is_err());
+    }
+
+    #[test]
+    pub fn test_parse_with_spans_nesting_limit() {
+        let nested = |depth: usize| "[".repeat(depth) + &"]".repeat(depth);
+
+        assert!(parse_with_spans(nested(MAX_DEPTH).as_bytes(), ParseOptions::default()).is_ok());
+        let error =
+            parse_with_spans(nested(200_000).as_bytes(), ParseOptions::default()).unwrap_err();
+        assert_eq!(Some(MAX_DEPTH as u64), error.position.map(|p| p.offset));
+    }
+}
@@ -0,0 +1,36 @@
+// Helpers shared by the tests of the binary encodings.
+
+use super::tokenizer::{parse_str, JsonValue};
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn unhex(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+// Documents as the tokenizer produces them, covering the values the
+// encodings store differently: integers of every width, floats that do and
+// do not fit in fewer bits, escapes and empty containers.
+const ROUND_TRIP_INPUTS: [&str; 3] = [
+    "{\"pairs\": [{\"x0\": 12.375, \"y0\": -45.0625, \"x1\": 179.99999999999997, \"y1\": -0.000001}]}",
+    "[0.1, 1e300, -0.0000001, 4294967296, -9007199254740993, 18446744073709551615, -1e19, 65505, 0.00006103515625]",
+    "{\"nested\": {\"empty\": {}, \"list\": [[], [\"\\u00e9\\n\"]], \"flags\": [true, false, null]}}",
+];
+
+pub fn assert_round_trips<E, D, Error>(encode: E, decode: D)
+where
+    E: Fn(&JsonValue) -> Vec<u8>,
+    D: Fn(&[u8]) -> Result<JsonValue, Error>,
+    Error: PartialEq + std::fmt::Debug,
+{
+    for json in ROUND_TRIP_INPUTS {
+        let value = parse_str(json).unwrap();
+
+        assert_eq!(Ok(value.clone()), decode(&encode(&value)), "{}", json);
+    }
+}
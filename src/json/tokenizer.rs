@@ -1,7 +1,17 @@
+// The original byte-at-a-time tokenizer. Apart from trailing commas in arrays,
+// which it has always accepted, it has none of the extensions of
+// lexer::ParseOptions. Lenient input is read with the lexer, via
+// de::from_reader_with_options or Lexer::with_options, which is also how the
+// CLI reads documents.
 use core::str;
-use std::{collections::HashMap, io::BufRead};
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Read},
+};
 
-#[derive(PartialEq, Debug)]
+use super::de::MAX_DEPTH;
+
+#[derive(PartialEq, Debug, Clone)]
 pub enum JsonValue {
     Object(HashMap<String, JsonValue>),
     Array(Vec<JsonValue>),
@@ -17,6 +27,8 @@ pub enum TokenizedError {
     InvalidChar,
     Invalid,
     EndOfString,
+    // Arrays and objects nested deeper than de::MAX_DEPTH.
+    TooDeep,
 }
 
 fn read_one_char<R>(reader: &mut R) -> Result<char, TokenizedError>
@@ -43,10 +55,91 @@ macro_rules! tokenized {
     };
 }
 
+// What the tokenizer has just read, for tracing. Object keys are reported as
+// strings, `Invalid` is the character that could not start a token.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum TraceToken<'a> {
+    BeginObject,
+    EndObject,
+    BeginArray,
+    EndArray,
+    Colon,
+    Comma,
+    String(&'a str),
+    Number(f64),
+    True,
+    False,
+    Null,
+    Invalid(char),
+}
+
+// Receives every token with the byte offset where it starts. Untraced
+// parsing uses `()`, whose calls compile away.
+pub trait Trace {
+    fn token(&mut self, offset: u64, token: TraceToken);
+}
+
+impl Trace for () {
+    fn token(&mut self, _: u64, _: TraceToken) {}
+}
+
+impl<F> Trace for F
+where
+    F: FnMut(u64, TraceToken),
+{
+    fn token(&mut self, offset: u64, token: TraceToken) {
+        self(offset, token)
+    }
+}
+
+// Counts the bytes taken from the reader, so traced tokens have offsets.
+struct OffsetReader<R> {
+    inner: R,
+    offset: u64,
+}
+
+impl<R> OffsetReader<R> {
+    // Where the byte read last started.
+    fn last_offset(&self) -> u64 {
+        self.offset.saturating_sub(1)
+    }
+}
+
+impl<R> Read for OffsetReader<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = self.inner.read(buf)?;
+        self.offset += count as u64;
+        Ok(count)
+    }
+}
+
+impl<R> BufRead for OffsetReader<R>
+where
+    R: BufRead,
+{
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.offset += amount as u64;
+        self.inner.consume(amount)
+    }
+}
+
 #[derive(PartialEq, Debug)]
 pub struct TokenizedResult {
     last_char_read: Option<char>,
-    result: JsonValue,
+    pub result: JsonValue,
+}
+
+#[allow(dead_code)]
+pub fn parse_str(input: &str) -> Result<JsonValue, TokenizedError> {
+    let mut reader = input.as_bytes();
+    tokenized!(&mut reader).map(|tokenized_result| tokenized_result.result)
 }
 
 fn read_number<R>(reader: &mut R, result: &mut String) -> Result<char, TokenizedError>
@@ -83,11 +176,46 @@ pub fn tokenized<R>(
 ) -> Result<TokenizedResult, TokenizedError>
 where
     R: BufRead,
+{
+    let mut reader = OffsetReader {
+        inner: reader,
+        offset: 0,
+    };
+    tokenize(&mut reader, last_char, &mut (), 0)
+}
+
+// Same as tokenized, reporting each token to `trace` as it is read. Offsets
+// count from where the reader was when called.
+pub fn tokenized_traced<R, T>(
+    reader: &mut R,
+    trace: &mut T,
+) -> Result<TokenizedResult, TokenizedError>
+where
+    R: BufRead,
+    T: Trace,
+{
+    let mut reader = OffsetReader {
+        inner: reader,
+        offset: 0,
+    };
+    tokenize(&mut reader, None, trace, 0)
+}
+
+fn tokenize<R, T>(
+    reader: &mut OffsetReader<R>,
+    last_char: Option<char>,
+    trace: &mut T,
+    depth: usize,
+) -> Result<TokenizedResult, TokenizedError>
+where
+    R: BufRead,
+    T: Trace,
 {
     let char = match last_char {
         Some(c) => c,
         None => read_until_not_space(reader)?,
     };
+    let start = reader.last_offset();
 
     match char {
         'n' => {
@@ -96,10 +224,13 @@ where
                 return Err(TokenizedError::Invalid);
             };
             match str::from_utf8_mut(&mut buf) {
-                Ok(str) if str == "ull" => Ok(TokenizedResult {
-                    last_char_read: None,
-                    result: JsonValue::NullValue,
-                }),
+                Ok(str) if str == "ull" => {
+                    trace.token(start, TraceToken::Null);
+                    Ok(TokenizedResult {
+                        last_char_read: None,
+                        result: JsonValue::NullValue,
+                    })
+                }
                 _ => Err(TokenizedError::Invalid),
             }
         }
@@ -109,10 +240,13 @@ where
                 return Err(TokenizedError::Invalid);
             };
             match str::from_utf8_mut(&mut buf) {
-                Ok(str) if str == "rue" => Ok(TokenizedResult {
-                    last_char_read: None,
-                    result: JsonValue::TrueValue,
-                }),
+                Ok(str) if str == "rue" => {
+                    trace.token(start, TraceToken::True);
+                    Ok(TokenizedResult {
+                        last_char_read: None,
+                        result: JsonValue::TrueValue,
+                    })
+                }
                 _ => Err(TokenizedError::Invalid),
             }
         }
@@ -122,10 +256,13 @@ where
                 return Err(TokenizedError::Invalid);
             };
             match str::from_utf8_mut(&mut buf) {
-                Ok(str) if str == "alse" => Ok(TokenizedResult {
-                    last_char_read: None,
-                    result: JsonValue::FalseValue,
-                }),
+                Ok(str) if str == "alse" => {
+                    trace.token(start, TraceToken::False);
+                    Ok(TokenizedResult {
+                        last_char_read: None,
+                        result: JsonValue::FalseValue,
+                    })
+                }
                 _ => Err(TokenizedError::Invalid),
             }
         }
@@ -141,6 +278,7 @@ where
                     Err(e) => return Err(e),
                 };
             }
+            trace.token(start, TraceToken::String(&result));
             Ok(TokenizedResult {
                 last_char_read: None,
                 result: JsonValue::String(result),
@@ -153,51 +291,57 @@ where
             let last_char = read_number(reader, &mut result);
             let parsed_result: Result<f64, std::num::ParseFloatError> = result.parse();
             match parsed_result {
-                Ok(c) => Ok(TokenizedResult {
-                    last_char_read: Some(last_char.unwrap()),
-                    result: JsonValue::Number(c),
-                }),
+                Ok(c) => {
+                    trace.token(start, TraceToken::Number(c));
+                    Ok(TokenizedResult {
+                        last_char_read: Some(last_char.unwrap()),
+                        result: JsonValue::Number(c),
+                    })
+                }
                 Err(_) => Err(TokenizedError::Invalid),
             }
         }
+        '[' | '{' if depth == MAX_DEPTH => Err(TokenizedError::TooDeep),
         '[' => {
+            trace.token(start, TraceToken::BeginArray);
             let mut result: Vec<JsonValue> = Vec::new();
-            loop {
-                let value = tokenized!(reader);
-                let Ok(next_token) = value else {
-                    println!("Val: {:?}", value);
-                    break;
-                };
-                println!("Token: {:?}", next_token.result);
+            // A `]` where an element could start ends an empty array, or one
+            // with a trailing comma.
+            let mut next_char = read_until_not_space(reader)?;
+            while next_char != ']' {
+                let next_token = tokenize(reader, Some(next_char), trace, depth + 1)?;
                 result.push(next_token.result);
 
                 let char = match next_token.last_char_read {
-                    Some(' ') | None => read_until_not_space(reader),
-                    Some(c) => Ok(c),
+                    Some(' ') | None => read_until_not_space(reader)?,
+                    Some(c) => c,
                 };
 
                 match char {
-                    Ok(',') => continue,
-                    Ok(']') => break,
-                    Ok(_) => return Err(TokenizedError::Invalid),
-                    Err(e) => return Err(e),
+                    ',' => trace.token(reader.last_offset(), TraceToken::Comma),
+                    ']' => break,
+                    _ => return Err(TokenizedError::Invalid),
                 }
+                next_char = read_until_not_space(reader)?;
             }
+            trace.token(reader.last_offset(), TraceToken::EndArray);
             Ok(TokenizedResult {
                 last_char_read: None,
                 result: JsonValue::Array(result),
             })
         }
         '{' => {
+            trace.token(start, TraceToken::BeginObject);
             let mut result = HashMap::<String, JsonValue>::new();
             loop {
                 let Ok(next_char) = read_until_not_space(reader) else {
                     return Err(TokenizedError::Invalid);
                 };
                 if next_char == '}' {
+                    trace.token(reader.last_offset(), TraceToken::EndObject);
                     break;
                 }
-                let Ok(value) = tokenized(reader, Some(next_char)) else {
+                let Ok(value) = tokenize(reader, Some(next_char), trace, depth + 1) else {
                     return Err(TokenizedError::Invalid);
                 };
                 match value.result {
@@ -208,20 +352,21 @@ where
                         if char != ':' {
                             return Err(TokenizedError::Invalid);
                         }
-                        let Ok(value) = tokenized!(reader) else {
-                            return Err(TokenizedError::Invalid);
-                        };
+                        trace.token(reader.last_offset(), TraceToken::Colon);
+                        let value = tokenize(reader, None, trace, depth + 1)?;
                         result.insert(key, value.result);
                         let char = match value.last_char_read {
                             Some(' ') | None => read_until_not_space(reader),
                             Some(c) => Ok(c),
                         }?;
                         if char == '}' {
+                            trace.token(reader.last_offset(), TraceToken::EndObject);
                             break;
                         }
                         if char != ',' {
                             return Err(TokenizedError::Invalid);
                         }
+                        trace.token(reader.last_offset(), TraceToken::Comma);
                     }
                     _ => return Err(TokenizedError::Invalid),
                 }
@@ -232,7 +377,7 @@ where
             })
         }
         c => {
-            println!("Invalid token: {:?}", c);
+            trace.token(start, TraceToken::Invalid(c));
             Err(TokenizedError::Invalid)
         }
     }
@@ -363,6 +508,42 @@ mod tests {
         );
     }
 
+    #[rstest]
+    #[case("{\"a\": [1, true], \"b\": \"x\"}", vec![
+        "0 BeginObject", "1 String(\"a\")", "4 Colon", "6 BeginArray", "7 Number(1.0)",
+        "8 Comma", "10 True", "14 EndArray", "15 Comma", "17 String(\"b\")", "20 Colon",
+        "22 String(\"x\")", "25 EndObject",
+    ])]
+    #[case("[ ]", vec!["0 BeginArray", "2 EndArray"])]
+    #[case("[1, ]", vec!["0 BeginArray", "1 Number(1.0)", "2 Comma", "4 EndArray"])]
+    pub fn test_tokenized_traced(#[case] input: &str, #[case] expected: Vec<&str>) {
+        let mut reader = buf_reader_from_str(input);
+        let mut tokens = Vec::new();
+        let mut trace =
+            |offset: u64, token: TraceToken| tokens.push(format!("{} {:?}", offset, token));
+
+        assert!(tokenized_traced(&mut reader, &mut trace).is_ok());
+        assert_eq!(expected, tokens);
+    }
+
+    #[rstest]
+    #[case("[1, nul]", vec!["0 BeginArray", "1 Number(1.0)", "2 Comma"])]
+    #[case("[1, @]", vec!["0 BeginArray", "1 Number(1.0)", "2 Comma", "4 Invalid('@')"])]
+    #[case("[[]", vec!["0 BeginArray", "1 BeginArray", "2 EndArray"])]
+    #[case("  @", vec!["2 Invalid('@')"])]
+    pub fn test_tokenized_traced_errors(#[case] input: &str, #[case] expected: Vec<&str>) {
+        let mut reader = buf_reader_from_str(input);
+        let mut tokens = Vec::new();
+        let mut trace =
+            |offset: u64, token: TraceToken| tokens.push(format!("{} {:?}", offset, token));
+
+        assert_eq!(
+            Err(TokenizedError::Invalid),
+            tokenized_traced(&mut reader, &mut trace)
+        );
+        assert_eq!(expected, tokens);
+    }
+
     #[test]
     pub fn test_tokenized_nested_array() {
         let input = "[1, 2, \"haha\", [\"f\", \"w\", 3], 4]";
@@ -430,6 +611,17 @@ mod tests {
         );
     }
 
+    #[rstest]
+    #[case("[", "]")]
+    #[case("{\"a\": ", "}")]
+    pub fn test_tokenized_nesting_limit(#[case] open: &str, #[case] close: &str) {
+        let nested = |depth: usize| open.repeat(depth) + "0" + &close.repeat(depth);
+
+        assert!(parse_str(&nested(MAX_DEPTH)).is_ok());
+        // Deep enough to overflow the stack without the limit.
+        assert_eq!(Err(TokenizedError::TooDeep), parse_str(&nested(200_000)));
+    }
+
     #[test]
     pub fn test_tokenized_err() {
         let input = "nxll";
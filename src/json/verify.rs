@@ -0,0 +1,545 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt, fs, io,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+};
+
+use super::{
+    pointer::escape_token,
+    schema::type_name,
+    tokenizer::{tokenized_traced, JsonValue, Trace, TraceToken},
+};
+
+// How json::tokenizer and serde_json disagree about a document. Rejections
+// carry the message of the parser that failed.
+#[derive(PartialEq, Debug)]
+pub enum Mismatch {
+    Kind {
+        tokenizer: &'static str,
+        serde_json: &'static str,
+    },
+    Number {
+        tokenizer: f64,
+        serde_json: f64,
+    },
+    String {
+        tokenizer: String,
+        serde_json: String,
+    },
+    Boolean {
+        tokenizer: bool,
+        serde_json: bool,
+    },
+    Length {
+        tokenizer: usize,
+        serde_json: usize,
+    },
+    OnlyTokenizerMember,
+    OnlySerdeJsonMember,
+    OnlyTokenizerRejects(String),
+    OnlySerdeJsonRejects(String),
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mismatch::Kind {
+                tokenizer,
+                serde_json,
+            } => write!(
+                f,
+                "tokenizer read {}, serde_json read {}",
+                tokenizer, serde_json
+            ),
+            Mismatch::Number {
+                tokenizer,
+                serde_json,
+            } => write!(
+                f,
+                "tokenizer read {:e} ({:#018x}), serde_json read {:e} ({:#018x})",
+                tokenizer,
+                tokenizer.to_bits(),
+                serde_json,
+                serde_json.to_bits()
+            ),
+            Mismatch::String {
+                tokenizer,
+                serde_json,
+            } => write!(
+                f,
+                "tokenizer read {:?}, serde_json read {:?}",
+                tokenizer, serde_json
+            ),
+            Mismatch::Boolean {
+                tokenizer,
+                serde_json,
+            } => write!(
+                f,
+                "tokenizer read {}, serde_json read {}",
+                tokenizer, serde_json
+            ),
+            Mismatch::Length {
+                tokenizer,
+                serde_json,
+            } => write!(
+                f,
+                "tokenizer read {} elements, serde_json read {}",
+                tokenizer, serde_json
+            ),
+            Mismatch::OnlyTokenizerMember => write!(f, "member only read by tokenizer"),
+            Mismatch::OnlySerdeJsonMember => write!(f, "member only read by serde_json"),
+            Mismatch::OnlyTokenizerRejects(error) => {
+                write!(f, "only tokenizer rejects the document: {}", error)
+            }
+            Mismatch::OnlySerdeJsonRejects(error) => {
+                write!(f, "only serde_json rejects the document: {}", error)
+            }
+        }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub struct Disagreement {
+    pub pointer: String,
+    // Where the tokenizer read the value, or its closest enclosing one. When
+    // only the tokenizer rejects, the last token it read; when only serde_json
+    // does, where serde_json stopped.
+    pub offset: Option<u64>,
+    pub mismatch: Mismatch,
+}
+
+// The names schema::type_name gives the tokenizer's values.
+fn serde_json_type_name(value: &serde_json::Value) -> &'static str {
+    match value {
+        serde_json::Value::Object(_) => "object",
+        serde_json::Value::Array(_) => "array",
+        serde_json::Value::String(_) => "string",
+        serde_json::Value::Number(_) => "number",
+        serde_json::Value::Bool(_) => "boolean",
+        serde_json::Value::Null => "null",
+    }
+}
+
+// Integers are converted the way the tokenizer stores every number, rounding
+// to the nearest f64.
+fn serde_json_number(number: &serde_json::Number) -> f64 {
+    if let Some(unsigned) = number.as_u64() {
+        unsigned as f64
+    } else if let Some(signed) = number.as_i64() {
+        signed as f64
+    } else {
+        number.as_f64().unwrap_or(f64::NAN)
+    }
+}
+
+fn child_pointer(pointer: &str, token: &str) -> String {
+    format!("{}/{}", pointer, escape_token(token))
+}
+
+// Numbers must have the same bits, so -0 and 0 differ. Members are compared
+// in key order to keep the report stable.
+fn compare(
+    pointer: String,
+    tokenizer: &JsonValue,
+    serde_json: &serde_json::Value,
+    found: &mut Vec<(String, Mismatch)>,
+) {
+    match (tokenizer, serde_json) {
+        (JsonValue::Object(t), serde_json::Value::Object(s)) => {
+            let keys: BTreeSet<&String> = t.keys().chain(s.keys()).collect();
+            for key in keys {
+                let child = child_pointer(&pointer, key);
+                match (t.get(key), s.get(key)) {
+                    (Some(t), Some(s)) => compare(child, t, s, found),
+                    (Some(_), None) => found.push((child, Mismatch::OnlyTokenizerMember)),
+                    _ => found.push((child, Mismatch::OnlySerdeJsonMember)),
+                }
+            }
+        }
+        (JsonValue::Array(t), serde_json::Value::Array(s)) => {
+            if t.len() != s.len() {
+                found.push((
+                    pointer.clone(),
+                    Mismatch::Length {
+                        tokenizer: t.len(),
+                        serde_json: s.len(),
+                    },
+                ));
+            }
+            for (i, (t, s)) in t.iter().zip(s).enumerate() {
+                compare(format!("{}/{}", pointer, i), t, s, found);
+            }
+        }
+        (JsonValue::String(t), serde_json::Value::String(s)) => {
+            if t != s {
+                found.push((
+                    pointer,
+                    Mismatch::String {
+                        tokenizer: t.clone(),
+                        serde_json: s.clone(),
+                    },
+                ));
+            }
+        }
+        (JsonValue::Number(t), serde_json::Value::Number(s)) => {
+            let s = serde_json_number(s);
+            if t.to_bits() != s.to_bits() {
+                found.push((
+                    pointer,
+                    Mismatch::Number {
+                        tokenizer: *t,
+                        serde_json: s,
+                    },
+                ));
+            }
+        }
+        (JsonValue::TrueValue | JsonValue::FalseValue, serde_json::Value::Bool(s)) => {
+            let t = *tokenizer == JsonValue::TrueValue;
+            if t != *s {
+                found.push((
+                    pointer,
+                    Mismatch::Boolean {
+                        tokenizer: t,
+                        serde_json: *s,
+                    },
+                ));
+            }
+        }
+        (JsonValue::NullValue, serde_json::Value::Null) => {}
+        _ => found.push((
+            pointer,
+            Mismatch::Kind {
+                tokenizer: type_name(tokenizer),
+                serde_json: serde_json_type_name(serde_json),
+            },
+        )),
+    }
+}
+
+enum Frame {
+    Array(usize),
+    Object(Option<String>),
+}
+
+// Follows the tokenizer's trace to record where each value starts, by
+// pointer, and where the last token was read.
+#[derive(Default)]
+struct Locator {
+    frames: Vec<Frame>,
+    offsets: HashMap<String, u64>,
+    last: Option<u64>,
+}
+
+impl Locator {
+    fn pointer(&self) -> String {
+        let mut pointer = String::new();
+        for frame in &self.frames {
+            match frame {
+                Frame::Array(i) => pointer.push_str(&format!("/{}", i)),
+                Frame::Object(key) => {
+                    pointer.push('/');
+                    pointer.push_str(&escape_token(key.as_deref().unwrap_or_default()));
+                }
+            }
+        }
+        pointer
+    }
+
+    // Falls back to the closest enclosing value the tokenizer read.
+    fn offset(&self, pointer: &str) -> Option<u64> {
+        let mut pointer = pointer;
+        loop {
+            if let Some(offset) = self.offsets.get(pointer) {
+                return Some(*offset);
+            }
+            pointer = &pointer[..pointer.rfind('/')?];
+        }
+    }
+}
+
+impl Trace for Locator {
+    fn token(&mut self, offset: u64, token: TraceToken) {
+        self.last = Some(offset);
+        match token {
+            TraceToken::Comma => match self.frames.last_mut() {
+                Some(Frame::Array(i)) => *i += 1,
+                Some(Frame::Object(key)) => *key = None,
+                None => {}
+            },
+            TraceToken::EndArray | TraceToken::EndObject => {
+                self.frames.pop();
+            }
+            TraceToken::Colon | TraceToken::Invalid(_) => {}
+            TraceToken::String(key) if matches!(self.frames.last(), Some(Frame::Object(None))) => {
+                self.frames.pop();
+                self.frames.push(Frame::Object(Some(key.to_string())));
+            }
+            value => {
+                self.offsets.insert(self.pointer(), offset);
+                match value {
+                    TraceToken::BeginArray => self.frames.push(Frame::Array(0)),
+                    TraceToken::BeginObject => self.frames.push(Frame::Object(None)),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+// serde_json reports 1-based lines and columns.
+fn byte_offset(input: &[u8], line: usize, column: usize) -> u64 {
+    let line_start: usize = input
+        .split_inclusive(|byte| *byte == b'\n')
+        .take(line.saturating_sub(1))
+        .map(<[u8]>::len)
+        .sum();
+    (line_start + column.saturating_sub(1)) as u64
+}
+
+// Parses `input` with both parsers and lists every way they disagree. An
+// input both of them reject is not a disagreement. A tokenizer panic counts as
+// a rejection, so one bad file does not stop a whole directory. Neither
+// parser recurses without bound, so deep nesting is rejected, not a crash.
+pub fn verify_bytes(input: &[u8]) -> Vec<Disagreement> {
+    let mut locator = Locator::default();
+    let tokenizer = panic::catch_unwind(AssertUnwindSafe(|| {
+        tokenized_traced(&mut &input[..], &mut locator)
+    }));
+    let tokenizer = match tokenizer {
+        Ok(Ok(result)) => Ok(result.result),
+        Ok(Err(error)) => Err(format!("{:?}", error)),
+        Err(_) => Err(String::from("tokenizer panicked")),
+    };
+    let serde_json = serde_json::from_slice::<serde_json::Value>(input);
+    match (tokenizer, serde_json) {
+        (Ok(tokenizer), Ok(serde_json)) => {
+            let mut found = Vec::new();
+            compare(String::new(), &tokenizer, &serde_json, &mut found);
+            found
+                .into_iter()
+                .map(|(pointer, mismatch)| Disagreement {
+                    offset: locator.offset(&pointer),
+                    pointer,
+                    mismatch,
+                })
+                .collect()
+        }
+        (Err(error), Ok(_)) => vec![Disagreement {
+            pointer: locator.pointer(),
+            offset: locator.last,
+            mismatch: Mismatch::OnlyTokenizerRejects(error),
+        }],
+        (Ok(_), Err(error)) => vec![Disagreement {
+            pointer: String::new(),
+            offset: Some(byte_offset(input, error.line(), error.column())),
+            mismatch: Mismatch::OnlySerdeJsonRejects(error.to_string()),
+        }],
+        (Err(_), Err(_)) => vec![],
+    }
+}
+
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries = fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+    for entry in entries {
+        // Symlinks are skipped, as a link to an ancestor would never end.
+        let file_type = fs::symlink_metadata(&entry)?.file_type();
+        if file_type.is_symlink() {
+            continue;
+        } else if file_type.is_dir() {
+            collect_files(&entry, files)?;
+        } else if entry
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
+
+// Verifies a file, or every .json file under a directory, in path order.
+pub fn verify_path(path: &Path) -> io::Result<Vec<(PathBuf, Vec<Disagreement>)>> {
+    let mut files = Vec::new();
+    if path.is_dir() {
+        collect_files(path, &mut files)?;
+    } else {
+        files.push(path.to_path_buf());
+    }
+    files
+        .into_iter()
+        .map(|file| {
+            let input = fs::read(&file)?;
+            Ok((file, verify_bytes(&input)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use rstest::rstest;
+
+    use crate::json::de;
+    use crate::json::verify::*;
+
+    #[rstest]
+    #[case("{\"a\": [1, 2.5, \"x\", true, null], \"b\": {\"c\": -3}}")]
+    #[case("[0.1, 123456789012345678, -0, \"\\u00e9\\n\"]")]
+    #[case("{\"a\": tru}")]
+    #[case("[1, 2 x]")]
+    pub fn test_verify_agreeing(#[case] input: &str) {
+        assert_eq!(Vec::<Disagreement>::new(), verify_bytes(input.as_bytes()));
+    }
+
+    // Both parsers reject it instead of overflowing the stack.
+    #[test]
+    pub fn test_verify_deep_nesting() {
+        let input = "[".repeat(200_000) + &"]".repeat(200_000);
+
+        assert_eq!(Vec::<Disagreement>::new(), verify_bytes(input.as_bytes()));
+    }
+
+    #[rstest]
+    #[case("[1] 2", Some(4))]
+    #[case("[1, 2,]", Some(6))]
+    #[case("[1]\n\n x", Some(6))]
+    pub fn test_verify_only_serde_json_rejects(#[case] input: &str, #[case] offset: Option<u64>) {
+        let disagreements = verify_bytes(input.as_bytes());
+
+        assert_eq!(1, disagreements.len());
+        assert_eq!(offset, disagreements[0].offset);
+        assert!(matches!(
+            disagreements[0].mismatch,
+            Mismatch::OnlySerdeJsonRejects(_)
+        ));
+    }
+
+    #[rstest]
+    #[case("{\"a\": [1,\n2]}", "/a/1", 9)]
+    #[case("{\"b\": {\"d\": 1e-7}}", "/b/d", 10)]
+    pub fn test_verify_only_tokenizer_rejects(
+        #[case] input: &str,
+        #[case] pointer: &str,
+        #[case] offset: u64,
+    ) {
+        assert_eq!(
+            vec![Disagreement {
+                pointer: String::from(pointer),
+                offset: Some(offset),
+                mismatch: Mismatch::OnlyTokenizerRejects(String::from("Invalid")),
+            }],
+            verify_bytes(input.as_bytes())
+        );
+    }
+
+    #[test]
+    pub fn test_compare() {
+        let tokenizer: JsonValue =
+            de::from_str("{\"a\": [1, 2], \"b\": \"x\", \"c\": 0.0, \"d~\": {}, \"f\": true}")
+                .unwrap();
+        let serde_json = serde_json::from_str(
+            "{\"a\": [1], \"b\": \"y\", \"c\": -0.0, \"d~\": [], \"e\": null, \"f\": false}",
+        )
+        .unwrap();
+        let mut found = Vec::new();
+
+        compare(String::new(), &tokenizer, &serde_json, &mut found);
+
+        assert_eq!(
+            vec![
+                (
+                    String::from("/a"),
+                    Mismatch::Length {
+                        tokenizer: 2,
+                        serde_json: 1
+                    }
+                ),
+                (
+                    String::from("/b"),
+                    Mismatch::String {
+                        tokenizer: String::from("x"),
+                        serde_json: String::from("y")
+                    }
+                ),
+                (
+                    String::from("/c"),
+                    Mismatch::Number {
+                        tokenizer: 0.0,
+                        serde_json: -0.0
+                    }
+                ),
+                (
+                    String::from("/d~0"),
+                    Mismatch::Kind {
+                        tokenizer: "object",
+                        serde_json: "array"
+                    }
+                ),
+                (String::from("/e"), Mismatch::OnlySerdeJsonMember),
+                (
+                    String::from("/f"),
+                    Mismatch::Boolean {
+                        tokenizer: true,
+                        serde_json: false
+                    }
+                ),
+            ],
+            found
+        );
+    }
+
+    #[test]
+    pub fn test_locator_offsets() {
+        let input = "{\"a\": [1, {\"b\": \"x\"}], \"c\": 3}";
+        let mut locator = Locator::default();
+
+        tokenized_traced(&mut input.as_bytes(), &mut locator).unwrap();
+
+        assert_eq!(
+            HashMap::from([
+                (String::from(""), 0),
+                (String::from("/a"), 6),
+                (String::from("/a/0"), 7),
+                (String::from("/a/1"), 10),
+                (String::from("/a/1/b"), 16),
+                (String::from("/c"), 28),
+            ]),
+            locator.offsets
+        );
+        assert_eq!(Some(16), locator.offset("/a/1/b/missing"));
+    }
+
+    #[test]
+    pub fn test_verify_path() {
+        let directory = env::temp_dir().join(format!("verify-{}", std::process::id()));
+        fs::create_dir_all(directory.join("nested")).unwrap();
+        fs::write(directory.join("a.json"), "[1, 2]").unwrap();
+        fs::write(directory.join("nested/b.json"), "[1,]").unwrap();
+        fs::write(directory.join("notes.txt"), "[").unwrap();
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&directory, directory.join("nested/loop")).unwrap();
+            std::os::unix::fs::symlink(directory.join("a.json"), directory.join("link.json"))
+                .unwrap();
+        }
+
+        let results = verify_path(&directory);
+        fs::remove_dir_all(&directory).unwrap();
+
+        let results = results.unwrap();
+        assert_eq!(
+            vec![directory.join("a.json"), directory.join("nested/b.json")],
+            results
+                .iter()
+                .map(|(file, _)| file.clone())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(0, results[0].1.len());
+        assert_eq!(1, results[1].1.len());
+    }
+}
@@ -0,0 +1,112 @@
+use std::io::{self, Write};
+
+use super::tokenizer::JsonValue;
+
+// Compact output with object keys in sorted order, so writing the same value
+// twice always produces the same bytes.
+pub fn write_value<W>(writer: &mut W, value: &JsonValue) -> io::Result<()>
+where
+    W: Write,
+{
+    match value {
+        JsonValue::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            writer.write_all(b"{")?;
+            for (i, key) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    writer.write_all(b",")?;
+                }
+                write_string(writer, key)?;
+                writer.write_all(b":")?;
+                write_value(writer, &map[key])?;
+            }
+            writer.write_all(b"}")
+        }
+        JsonValue::Array(items) => {
+            writer.write_all(b"[")?;
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    writer.write_all(b",")?;
+                }
+                write_value(writer, item)?;
+            }
+            writer.write_all(b"]")
+        }
+        JsonValue::String(str) => write_string(writer, str),
+        JsonValue::Number(number) => write_number(writer, *number),
+        JsonValue::TrueValue => writer.write_all(b"true"),
+        JsonValue::FalseValue => writer.write_all(b"false"),
+        JsonValue::NullValue => writer.write_all(b"null"),
+    }
+}
+
+// JSON has no representation for NaN or the infinities, they are written as
+// null. Debug formatting keeps the shortest representation that round-trips.
+pub fn write_number<W>(writer: &mut W, number: f64) -> io::Result<()>
+where
+    W: Write,
+{
+    if !number.is_finite() {
+        return writer.write_all(b"null");
+    }
+    write!(writer, "{:?}", number)
+}
+
+pub fn write_string<W>(writer: &mut W, str: &str) -> io::Result<()>
+where
+    W: Write,
+{
+    writer.write_all(b"\"")?;
+    for c in str.chars() {
+        match c {
+            '"' => writer.write_all(b"\\\"")?,
+            '\\' => writer.write_all(b"\\\\")?,
+            '\n' => writer.write_all(b"\\n")?,
+            '\r' => writer.write_all(b"\\r")?,
+            '\t' => writer.write_all(b"\\t")?,
+            '\u{0008}' => writer.write_all(b"\\b")?,
+            '\u{000C}' => writer.write_all(b"\\f")?,
+            c if (c as u32) < 0x20 => write!(writer, "\\u{:04x}", c as u32)?,
+            c => write!(writer, "{}", c)?,
+        }
+    }
+    writer.write_all(b"\"")
+}
+
+pub fn to_string(value: &JsonValue) -> String {
+    let mut buffer = Vec::new();
+    write_value(&mut buffer, value).unwrap();
+    String::from_utf8(buffer).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::json::tokenizer::parse_str;
+    use crate::json::writer::*;
+
+    #[rstest]
+    #[case("null", "null")]
+    #[case("[1, 2.5, true, false]", "[1.0,2.5,true,false]")]
+    #[case("{\"b\": 1, \"a\": [ ]}", "{\"a\":[],\"b\":1.0}")]
+    #[case("\"tab\\there \\\"quoted\\\"\"", "\"tab\\there \\\"quoted\\\"\"")]
+    #[case("-1E300", "-1e300")]
+    pub fn test_to_string(#[case] input: &str, #[case] expected: &str) {
+        assert_eq!(expected, to_string(&parse_str(input).unwrap()));
+    }
+
+    #[test]
+    pub fn test_write_non_finite_number() {
+        assert_eq!("null", to_string(&JsonValue::Number(f64::NAN)));
+    }
+
+    #[test]
+    pub fn test_write_control_char() {
+        assert_eq!(
+            "\"\\u0001\"",
+            to_string(&JsonValue::String("\u{0001}".to_string()))
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use std::env;
 
 use repetition::file_test::file_read_test;
 
@@ -7,5 +8,9 @@ mod json;
 mod repetition;
 
 fn main() {
-    file_read_test();
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("json") => json::cli::run(&args[1..]),
+        _ => file_read_test(),
+    }
 }